@@ -31,6 +31,11 @@ fn main() {
         _ => println!("cargo:rustc-env=ENCLAVE_FILE_NAME=libhello_rust_enclave.signed.so"),
     }
 
+    // the enclave code is linked into the app, without the SGX SDK
+    if env::var_os("CARGO_FEATURE_NATIVE").is_some() {
+        return;
+    }
+
     // generate enclave_u src
     let status = Command::new(sgx_sdk_dir.join("bin/x64/sgx_edger8r"))
         .arg("--untrusted")
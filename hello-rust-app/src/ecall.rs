@@ -1,9 +1,13 @@
-use crate::error::Result;
-use crate::utils::SharedSgxEnclave;
-use anyhow::Ok;
+use crate::error::{Context, Result};
+use crate::utils::{HostContext, ResultStream, SharedSgxEnclave, StreamConfig};
+use hello_rust_core::{
+    api::EnclaveApiClient,
+    ecall::{EcallError, EcallTransport},
+};
 
 use sgx_types::*;
 
+#[cfg(not(feature = "native"))]
 mod ffi {
     #![allow(clippy::all)]
     #![allow(dead_code)]
@@ -12,31 +16,105 @@ mod ffi {
     include!(concat!(env!("OUT_DIR"), "/enclave_ffi.rs"));
 }
 
+#[cfg(feature = "native")]
+use crate::native::ffi;
+
+/// Check the status of an ecall.
+fn check_status(sgx_ret: sgx_status_t) -> Result<(), EcallError> {
+    match sgx_ret {
+        sgx_status_t::SGX_SUCCESS => Ok(()),
+        _ => Err(EcallError::Sgx(sgx_ret as u32)),
+    }
+}
+
+/// Check the status and the return code of an ecall.
+fn check_ecall(sgx_ret: sgx_status_t, retval: i32) -> Result<(), EcallError> {
+    check_status(sgx_ret)?;
+    match retval {
+        0 => Ok(()),
+        _ => Err(EcallError::Enclave(retval)),
+    }
+}
+
+/// Compute the L2 distance of the point pair of `query_key`, streaming the result back.
 pub fn enclave_compute_l2_distance(
     enclave: &SharedSgxEnclave,
-    query_key: usize
-) -> Result<()> {
+    query_key: usize,
+    config: &StreamConfig,
+) -> Result<ResultStream> {
+    let enclave = enclave.clone();
+    ResultStream::spawn(enclave.geteid(), config, move |frame_size| {
+        let mut retval = 0;
+        let sgx_ret = unsafe {
+            ffi::ecall_sgx_l2_dist(
+                enclave.geteid(),
+                &mut retval as *mut _,
+                query_key,
+                frame_size,
+            )
+        };
+        check_ecall(sgx_ret, retval).context("ecall_sgx_l2_dist failed")
+    })
+}
 
-    let mut retval = 0;
-    let sgx_ret = unsafe {
-        ffi::ecall_sgx_l2_dist(
-            enclave.geteid(),
-            &mut retval as *mut _,
-            query_key,
-        )
-    };
-    match sgx_ret {
-        sgx_status_t::SGX_SUCCESS => {
-            if retval != 0 {
-                Err(anyhow::anyhow!("ecall_sgx_l2_dist failed: {}", retval))
-            } else {
-                Ok(())
-            }
-        }
-        _ => Err(anyhow::anyhow!("ecall_sgx_l2_dist failed: {}", sgx_ret)),
+/// Compute the L2 distances of the point pairs of `keys`, which the enclave fetches in batches,
+/// streaming the results back in order.
+pub fn enclave_compute_l2_distance_batch(
+    enclave: &SharedSgxEnclave,
+    keys: Vec<usize>,
+    config: &StreamConfig,
+) -> Result<ResultStream> {
+    let enclave = enclave.clone();
+    ResultStream::spawn(enclave.geteid(), config, move |frame_size| {
+        let mut retval = 0;
+        let sgx_ret = unsafe {
+            ffi::ecall_sgx_l2_dist_batch(
+                enclave.geteid(),
+                &mut retval as *mut _,
+                keys.as_ptr(),
+                keys.len(),
+                frame_size,
+            )
+        };
+        check_ecall(sgx_ret, retval).context("ecall_sgx_l2_dist_batch failed")
+    })
+}
+
+/// Transport of typed API calls through `ecall_api`, with the response returned by
+/// `ocall_api_response`.
+#[derive(Clone)]
+pub struct SgxTransport {
+    enclave: SharedSgxEnclave,
+}
+
+impl EcallTransport for SgxTransport {
+    fn ecall(&self, request: &[u8]) -> Result<Vec<u8>, EcallError> {
+        let eid = self.enclave.geteid();
+        let ctx = HostContext::get(eid)
+            .ok_or_else(|| EcallError::Message(format!("no host context for enclave {eid}")))?;
+        let guard = ctx.enter_api();
+        let mut retval = 0;
+        let sgx_ret = unsafe {
+            ffi::ecall_api(
+                eid,
+                &mut retval as *mut _,
+                request.as_ptr(),
+                request.len(),
+            )
+        };
+        let response = guard.take_response();
+        check_ecall(sgx_ret, retval)?;
+        response.ok_or(EcallError::NoResponse)
     }
 }
 
+/// Typed API of `enclave`.
+pub fn enclave_api(enclave: &SharedSgxEnclave) -> EnclaveApiClient<SgxTransport> {
+    EnclaveApiClient(SgxTransport {
+        enclave: enclave.clone(),
+    })
+}
+
 pub fn enclave_add(
     enclave: &SharedSgxEnclave,
     a: f64,
@@ -51,10 +129,6 @@ pub fn enclave_add(
             b,
         )
     };
-    match sgx_ret {
-        sgx_status_t::SGX_SUCCESS => {
-                Ok(retval)
-        }
-        _ => Err(anyhow::anyhow!("ecall_add failed: {}", sgx_ret)),
-    }
+    check_status(sgx_ret).context("ecall_add failed")?;
+    Ok(retval)
 }
\ No newline at end of file
@@ -1,14 +1,17 @@
-use std::collections::HashMap;
-
 pub use anyhow as error;
-use ecall::enclave_add;
-use hello_rust_core::util::Point;
-use utils::TEEJoinWorkerFactory;
+use ecall::{enclave_add, enclave_api};
+use hello_rust_core::{api::EnclaveApi, util::Point};
+use utils::{StreamConfig, TEEJoinWorkerFactory};
+
+use crate::ecall::enclave_compute_l2_distance_batch;
 
-use crate::{ocall::{POINT_PAIR_BYTES_BUFFER, POINT_PAIR_MAP, RESULTS_BUFFER}, ecall::enclave_compute_l2_distance};
 
+#[cfg(feature = "native")]
+extern crate alloc;
 
 pub mod ecall;
+#[cfg(feature = "native")]
+pub mod native;
 pub mod ocall;
 pub mod utils;
 
@@ -17,34 +20,35 @@ fn main() {
     let add = enclave_add(&enclave.enclave, 1.0, 2.0).unwrap();
     println!("add: {}", add);
 
-    unsafe {
-        POINT_PAIR_MAP = Some(HashMap::new());
-        POINT_PAIR_BYTES_BUFFER = Some(HashMap::new());
-        RESULTS_BUFFER = Some(Vec::new());
-
-        let mut point_pair_map = POINT_PAIR_MAP.as_mut().unwrap();
-        point_pair_map.insert(
-            1, 
-            (
-                Point{point_vec: vec![1.0, 0.0]},
-                Point{point_vec: vec![0.0, 1.0]},
-            )
-        );
-        point_pair_map.insert(
-            2, 
-            (
-                Point{point_vec: vec![0.0, 0.0]},
-                Point{point_vec: vec![3.0, 4.0]},
-            )
-        );
+    let context = enclave.context();
+    context.insert_point_pair(
+        1,
+        (
+            Point{point_vec: vec![1.0, 0.0]},
+            Point{point_vec: vec![0.0, 1.0]},
+        )
+    );
+    context.insert_point_pair(
+        2,
+        (
+            Point{point_vec: vec![0.0, 0.0]},
+            Point{point_vec: vec![3.0, 4.0]},
+        )
+    );
+    let results = enclave_compute_l2_distance_batch(
+        &enclave.enclave,
+        vec![1, 2],
+        &StreamConfig::default(),
+    ).unwrap();
+    for res in results {
+        println!("{:?}", res.unwrap())
     }
-    enclave_compute_l2_distance(&enclave.enclave, 1).unwrap();
-    enclave_compute_l2_distance(&enclave.enclave, 2).unwrap();
 
-    let result_list = unsafe {
-        RESULTS_BUFFER.as_ref().unwrap().clone()
-    };
-    for res in result_list {
-        println!("{:?}", res)
+    let pairs = vec![(
+        Point{point_vec: vec![1.0, 1.0]},
+        Point{point_vec: vec![4.0, 5.0]},
+    )];
+    for res in enclave_api(&enclave.enclave).l2_dist(pairs).unwrap() {
+        println!("{res:?}")
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,272 @@
+//! Enclave code linked into the host process, which replaces the SGX enclave with the `native`
+//! feature.
+use sgx_types::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[allow(clippy::all)]
+#[path = "../../hello-rust-enclave/src/enclave_code.rs"]
+mod enclave_code;
+
+/// Next id of a native enclave.
+static NEXT_EID: AtomicU64 = AtomicU64::new(1);
+
+/// Enclave whose code runs in the host process, in place of `SgxEnclave`.
+#[derive(Debug)]
+pub struct NativeEnclave {
+    eid: sgx_enclave_id_t,
+}
+
+impl NativeEnclave {
+    pub fn create() -> Self {
+        Self {
+            eid: NEXT_EID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    #[inline]
+    pub fn geteid(&self) -> sgx_enclave_id_t {
+        self.eid
+    }
+}
+
+/// Ecalls with the signatures of the untrusted stubs generated by `sgx_edger8r`.
+pub(crate) mod ffi {
+    use super::enclave_code;
+    use sgx_types::*;
+
+    pub unsafe fn ecall_sgx_add(
+        _eid: sgx_enclave_id_t,
+        retval: *mut f64,
+        a: f64,
+        b: f64,
+    ) -> sgx_status_t {
+        *retval = enclave_code::ecall_sgx_add(a, b);
+        sgx_status_t::SGX_SUCCESS
+    }
+
+    pub unsafe fn ecall_sgx_l2_dist(
+        _eid: sgx_enclave_id_t,
+        retval: *mut i32,
+        key: usize,
+        frame_size: usize,
+    ) -> sgx_status_t {
+        *retval = enclave_code::ecall_sgx_l2_dist(key, frame_size);
+        sgx_status_t::SGX_SUCCESS
+    }
+
+    pub unsafe fn ecall_sgx_l2_dist_batch(
+        _eid: sgx_enclave_id_t,
+        retval: *mut i32,
+        keys: *const usize,
+        num_keys: usize,
+        frame_size: usize,
+    ) -> sgx_status_t {
+        *retval = enclave_code::ecall_sgx_l2_dist_batch(keys, num_keys, frame_size);
+        sgx_status_t::SGX_SUCCESS
+    }
+
+    pub unsafe fn ecall_api(
+        _eid: sgx_enclave_id_t,
+        retval: *mut i32,
+        request: *const u8,
+        request_len: usize,
+    ) -> sgx_status_t {
+        *retval = enclave_code::ecall_api(request, request_len);
+        sgx_status_t::SGX_SUCCESS
+    }
+}
+
+/// Ocalls with the signatures of the trusted stubs generated by `sgx_edger8r`, which the enclave
+/// code links to.
+mod ocall_bridge {
+    use crate::ocall;
+    use sgx_types::*;
+
+    #[no_mangle]
+    pub unsafe extern "C" fn ocall_fetch_point_pairs(
+        retval: *mut i32,
+        keys: *const usize,
+        num_keys: usize,
+        buf: *mut u8,
+        buf_len: usize,
+        num_fetched: *mut usize,
+        required_len: *mut usize,
+    ) -> sgx_status_t {
+        *retval =
+            ocall::ocall_fetch_point_pairs(keys, num_keys, buf, buf_len, num_fetched, required_len);
+        sgx_status_t::SGX_SUCCESS
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn ocall_return_results(
+        retval: *mut i32,
+        frame: *const u8,
+        frame_len: usize,
+    ) -> sgx_status_t {
+        *retval = ocall::ocall_return_results(frame, frame_len);
+        sgx_status_t::SGX_SUCCESS
+    }
+
+    #[no_mangle]
+    pub unsafe extern "C" fn ocall_api_response(
+        retval: *mut i32,
+        response: *const u8,
+        response_len: usize,
+    ) -> sgx_status_t {
+        *retval = ocall::ocall_api_response(response, response_len);
+        sgx_status_t::SGX_SUCCESS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecall::*;
+    use crate::utils::{StreamConfig, TEEJoinWorkerFactory};
+    use hello_rust_core::{api::EnclaveApi, ecall::EcallError, util::Point};
+    use std::path::Path;
+
+    fn point(point_vec: Vec<f64>) -> Point {
+        Point { point_vec }
+    }
+
+    fn factory() -> TEEJoinWorkerFactory {
+        let enclave = TEEJoinWorkerFactory::new(Path::new("native")).unwrap();
+        for key in 0..100 {
+            let x = key as f64;
+            enclave
+                .context()
+                .insert_point_pair(key, (point(vec![0.0, 0.0]), point(vec![3.0 * x, 4.0 * x])));
+        }
+        enclave
+    }
+
+    #[test]
+    fn test_add() {
+        let enclave = factory();
+        assert_eq!(enclave_add(&enclave.enclave, 1.0, 2.0).unwrap(), 3.0);
+    }
+
+    #[test]
+    fn test_l2_dist() {
+        let enclave = factory();
+        let results: Vec<_> =
+            enclave_compute_l2_distance(&enclave.enclave, 3, &StreamConfig::default())
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].dist, 15.0);
+    }
+
+    #[test]
+    fn test_l2_dist_batch() {
+        let enclave = factory();
+        let keys: Vec<usize> = (0..100).rev().collect();
+        // Small frames and channel to flush many times with backpressure.
+        let config = StreamConfig {
+            frame_size: 64,
+            channel_capacity: 1,
+        };
+        let dists: Vec<f64> =
+            enclave_compute_l2_distance_batch(&enclave.enclave, keys.clone(), &config)
+                .unwrap()
+                .map(|res| res.unwrap().dist)
+                .collect();
+        let expected: Vec<f64> = keys.iter().map(|&key| 5.0 * key as f64).collect();
+        assert_eq!(dists, expected);
+    }
+
+    #[test]
+    fn test_l2_dist_many_keys() {
+        let enclave = factory();
+        let dists: Vec<f64> = enclave_compute_l2_distance_batch(
+            &enclave.enclave,
+            vec![1; 5000],
+            &StreamConfig::default(),
+        )
+        .unwrap()
+        .map(|res| res.unwrap().dist)
+        .collect();
+        assert_eq!(dists, vec![5.0; 5000]);
+    }
+
+    #[test]
+    fn test_l2_dist_large_pair() {
+        let enclave = factory();
+        // A pair of 80 KB, larger than the initial fetch buffer.
+        let dim = 5000;
+        enclave
+            .context()
+            .insert_point_pair(100, (point(vec![0.0; dim]), point(vec![1.0; dim])));
+        // A pair of 400 KB, larger than the limit of the fetch buffer.
+        enclave
+            .context()
+            .insert_point_pair(101, (point(vec![0.0; 5 * dim]), point(vec![1.0; 5 * dim])));
+
+        let results: Vec<_> = enclave_compute_l2_distance_batch(
+            &enclave.enclave,
+            vec![1, 100],
+            &StreamConfig::default(),
+        )
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+        assert_eq!(results[1].dist, (dim as f64).sqrt());
+
+        let mut results =
+            enclave_compute_l2_distance(&enclave.enclave, 101, &StreamConfig::default()).unwrap();
+        assert!(results.next().unwrap().is_err());
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn test_l2_dist_missing_key() {
+        let enclave = factory();
+        let mut results = enclave_compute_l2_distance_batch(
+            &enclave.enclave,
+            vec![1, 100],
+            &StreamConfig::default(),
+        )
+        .unwrap();
+        assert!(results.next().unwrap().is_err());
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn test_l2_dist_zero_frame_size() {
+        let enclave = factory();
+        let config = StreamConfig {
+            frame_size: 0,
+            ..Default::default()
+        };
+        let mut results = enclave_compute_l2_distance(&enclave.enclave, 3, &config).unwrap();
+        assert!(results.next().unwrap().is_err());
+        assert!(results.next().is_none());
+    }
+
+    #[test]
+    fn test_enclave_api() {
+        let enclave = factory();
+        let pairs = vec![
+            (point(vec![1.0, 0.0]), point(vec![0.0, 1.0])),
+            (point(vec![0.0, 0.0]), point(vec![3.0, 4.0])),
+        ];
+        let dists: Vec<f64> = enclave_api(&enclave.enclave)
+            .l2_dist(pairs)
+            .unwrap()
+            .iter()
+            .map(|l2| l2.dist)
+            .collect();
+        assert_eq!(dists, vec![2f64.sqrt(), 5.0]);
+    }
+
+    #[test]
+    fn test_enclave_api_dimension_mismatch() {
+        let enclave = factory();
+        let pairs = vec![(point(vec![1.0, 0.0]), point(vec![0.0, 1.0, 2.0]))];
+        assert!(matches!(
+            enclave_api(&enclave.enclave).l2_dist(pairs),
+            Err(EcallError::Message(_))
+        ));
+    }
+}
@@ -1,55 +1,102 @@
-use std::{collections::HashMap, slice, ptr::copy_nonoverlapping};
+use std::{ptr::copy_nonoverlapping, slice};
 
-use hello_rust_core::util::{Point, L2Dist};
-use serde::de::DeserializeOwned;
+use hello_rust_core::util::L2Dist;
 
-pub static mut POINT_PAIR_MAP: Option<HashMap<usize, (Point, Point)>> = Option::None;
-pub static mut POINT_PAIR_BYTES_BUFFER: Option<HashMap<usize, Vec<u8>>> = Option::None;
+use crate::utils::HostContext;
 
-pub static mut RESULTS_BUFFER: Option<Vec<L2Dist>> = Option::None;
+// With the `native` feature, the ocalls are not exported under their names, since the enclave
+// code links to the wrappers in `native` with the signatures of the trusted stubs instead.
 
-unsafe fn from_bytes<T: DeserializeOwned>(bytes: *const u8, bytes_len: usize) -> T {
-    let buf = slice::from_raw_parts(bytes, bytes_len);
-    let data: T = postcard::from_bytes(buf).unwrap();
-    data
+/// Error codes returned by ocalls. Zero means success.
+#[repr(i32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcallError {
+    /// The calling thread did not enter a host context before the ecall.
+    NoContext = 1,
+    /// The key is not found.
+    KeyNotFound = 2,
+    Serialize = 3,
+    Deserialize = 4,
+    /// The caller dropped the results of the ecall, or does not take results.
+    Closed = 5,
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ocall_return_result(result_bytes: *const u8, bytes_len: usize) -> i32 {
-    let data: L2Dist = from_bytes(result_bytes, bytes_len);
-    RESULTS_BUFFER.as_mut().unwrap().push(data);
-    0
+/// Return the encoded `response` of a typed API call to the caller of the ecall.
+///
+/// # Safety
+///
+/// `response` must be valid for reads of `response_len` bytes.
+#[cfg_attr(not(feature = "native"), no_mangle)]
+pub unsafe extern "C" fn ocall_api_response(response: *const u8, response_len: usize) -> i32 {
+    HostContext::with_current(|call| {
+        let bytes = if response_len == 0 {
+            Vec::new()
+        } else {
+            slice::from_raw_parts(response, response_len).to_vec()
+        };
+        call.set_response(bytes);
+        Ok(())
+    })
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ocall_get_outside_data_len(
-    query_key: usize,
-    data_len: *mut usize,
-) -> i32 {
-    let query_data = POINT_PAIR_MAP
-        .as_mut()
-        .unwrap()
-        .get(&query_key)
-        .unwrap();
-    let data_bytes = postcard::to_allocvec(&vec![query_data.0.clone(), query_data.1.clone()]).unwrap();
-
-    *data_len = data_bytes.len();
-
-    POINT_PAIR_BYTES_BUFFER.as_mut().unwrap().insert(query_key, data_bytes);
-    0
+/// Send the results in `frame`, each encoded by postcard, to the caller of the ecall.
+///
+/// Blocks while the caller is not keeping up with the results.
+///
+/// # Safety
+///
+/// `frame` must be valid for reads of `frame_len` bytes.
+#[cfg_attr(not(feature = "native"), no_mangle)]
+pub unsafe extern "C" fn ocall_return_results(frame: *const u8, frame_len: usize) -> i32 {
+    HostContext::with_current(|call| {
+        if frame_len == 0 {
+            return Ok(());
+        }
+        let mut bytes = slice::from_raw_parts(frame, frame_len);
+        while !bytes.is_empty() {
+            let (result, rest): (L2Dist, _) =
+                postcard::take_from_bytes(bytes).map_err(|_| OcallError::Deserialize)?;
+            call.send_result(result)?;
+            bytes = rest;
+        }
+        Ok(())
+    })
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ocall_outside_data(
-    query_key: usize,
-    data: *mut u8,
-    data_len: usize,
+/// Write the point pairs of `keys` into `buf` in order, each encoded by postcard, as many as fit.
+///
+/// `num_fetched` is the number of pairs written. If not all pairs fit, `required_len` is the
+/// encoded size of the first pair which does not fit, otherwise it is zero.
+///
+/// # Safety
+///
+/// `keys` must be valid for reads of `num_keys` keys, `buf` must be valid for writes of `buf_len`
+/// bytes, and `num_fetched` and `required_len` must be valid for writes.
+#[cfg_attr(not(feature = "native"), no_mangle)]
+pub unsafe extern "C" fn ocall_fetch_point_pairs(
+    keys: *const usize,
+    num_keys: usize,
+    buf: *mut u8,
+    buf_len: usize,
+    num_fetched: *mut usize,
+    required_len: *mut usize,
 ) -> i32 {
-    let bytes = POINT_PAIR_BYTES_BUFFER.as_ref().unwrap().get(&query_key).unwrap();
-    copy_nonoverlapping(
-        bytes.as_ptr(),
-        data,
-        data_len,
-    );
-    0
-}
\ No newline at end of file
+    HostContext::with_current(|call| {
+        let keys = slice::from_raw_parts(keys, num_keys);
+        let mut offset = 0;
+        *num_fetched = 0;
+        *required_len = 0;
+        for &key in keys {
+            let pair = call.host.point_pair(key).ok_or(OcallError::KeyNotFound)?;
+            let bytes = postcard::to_allocvec(&pair).map_err(|_| OcallError::Serialize)?;
+            if bytes.len() > buf_len - offset {
+                *required_len = bytes.len();
+                break;
+            }
+            copy_nonoverlapping(bytes.as_ptr(), buf.add(offset), bytes.len());
+            offset += bytes.len();
+            *num_fetched += 1;
+        }
+        Ok(())
+    })
+}
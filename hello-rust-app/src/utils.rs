@@ -5,3 +5,9 @@ pub use path::*;
 
 mod enclave;
 pub use enclave::*;
+
+mod context;
+pub use context::*;
+
+mod stream;
+pub use stream::*;
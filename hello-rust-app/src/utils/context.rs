@@ -0,0 +1,145 @@
+use super::*;
+use crate::ocall::OcallError;
+use hello_rust_core::util::{L2Dist, Point};
+use sgx_types::sgx_enclave_id_t;
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    sync::{mpsc::SyncSender, Arc, Mutex, MutexGuard, PoisonError, RwLock},
+};
+
+/// Host contexts of all enclaves, by enclave id.
+static REGISTRY: RwLock<BTreeMap<sgx_enclave_id_t, Arc<HostContext>>> =
+    RwLock::new(BTreeMap::new());
+
+thread_local! {
+    /// Context of the ecall which the current thread is running. Ocalls run on the thread which
+    /// made the ecall, so they find their enclave here.
+    static CURRENT: RefCell<Option<CallContext>> = RefCell::new(None);
+}
+
+/// Lock `mutex` even if another thread panicked while holding it.
+#[inline]
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Host-side state of one enclave, which is accessed by its ocalls.
+#[derive(Debug, Default)]
+pub struct HostContext {
+    point_pairs: Mutex<HashMap<usize, (Point, Point)>>,
+}
+
+impl HostContext {
+    /// Create a context for enclave `eid` and register it.
+    pub fn register(eid: sgx_enclave_id_t) -> Arc<Self> {
+        let ctx = Arc::new(Self::default());
+        REGISTRY
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(eid, ctx.clone());
+        ctx
+    }
+
+    /// Remove the context of enclave `eid`.
+    pub fn unregister(eid: sgx_enclave_id_t) {
+        REGISTRY
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&eid);
+    }
+
+    /// The context of enclave `eid`.
+    pub fn get(eid: sgx_enclave_id_t) -> Option<Arc<Self>> {
+        REGISTRY
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&eid)
+            .cloned()
+    }
+
+    /// Make this context current for ocalls on this thread until the guard is dropped, with
+    /// results sent to `results`. Call it before every ecall which makes ocalls.
+    pub fn enter(self: &Arc<Self>, results: SyncSender<L2Dist>) -> ContextGuard {
+        self.enter_call(Some(results))
+    }
+
+    /// Make this context current for a typed API call, whose response is taken by
+    /// `ContextGuard::take_response`.
+    pub(crate) fn enter_api(self: &Arc<Self>) -> ContextGuard {
+        self.enter_call(None)
+    }
+
+    fn enter_call(self: &Arc<Self>, results: Option<SyncSender<L2Dist>>) -> ContextGuard {
+        let call = CallContext {
+            host: self.clone(),
+            results,
+            response: RefCell::new(None),
+        };
+        let prev = CURRENT.with(|current| current.replace(Some(call)));
+        ContextGuard { prev }
+    }
+
+    /// Run `f` on the current context and convert the result to an ocall return code.
+    pub(crate) fn with_current<F>(f: F) -> i32
+    where
+        F: FnOnce(&CallContext) -> Result<(), OcallError>,
+    {
+        CURRENT.with(|current| match *current.borrow() {
+            Some(ref call) => match f(call) {
+                Ok(()) => 0,
+                Err(err) => err as i32,
+            },
+            None => OcallError::NoContext as i32,
+        })
+    }
+
+    pub fn insert_point_pair(&self, key: usize, pair: (Point, Point)) {
+        lock(&self.point_pairs).insert(key, pair);
+    }
+
+    pub(crate) fn point_pair(&self, key: usize) -> Option<(Point, Point)> {
+        lock(&self.point_pairs).get(&key).cloned()
+    }
+}
+
+/// Context of a running ecall.
+pub(crate) struct CallContext {
+    pub(crate) host: Arc<HostContext>,
+    results: Option<SyncSender<L2Dist>>,
+    /// Encoded response of a typed API call.
+    response: RefCell<Option<Vec<u8>>>,
+}
+
+impl CallContext {
+    /// Send a result to the caller of the ecall, blocking while the channel is full.
+    pub(crate) fn send_result(&self, result: L2Dist) -> Result<(), OcallError> {
+        let results = self.results.as_ref().ok_or(OcallError::Closed)?;
+        results.send(result).map_err(|_| OcallError::Closed)
+    }
+
+    /// Keep the encoded `response` of a typed API call for the caller.
+    pub(crate) fn set_response(&self, response: Vec<u8>) {
+        *self.response.borrow_mut() = Some(response);
+    }
+}
+
+/// Guard returned by `HostContext::enter`, which restores the previous context on drop.
+#[must_use]
+pub struct ContextGuard {
+    prev: Option<CallContext>,
+}
+
+impl ContextGuard {
+    /// Take the response of the typed API call made in this context.
+    pub(crate) fn take_response(&self) -> Option<Vec<u8>> {
+        CURRENT.with(|current| current.borrow().as_ref()?.response.take())
+    }
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let prev = self.prev.take();
+        CURRENT.with(|current| *current.borrow_mut() = prev);
+    }
+}
@@ -1,17 +1,29 @@
 use super::*;
-use std::{mem, path::Path, sync::Arc};
+use std::{path::Path, sync::Arc};
 
+#[cfg(feature = "native")]
+use crate::native::NativeEnclave;
+#[cfg(not(feature = "native"))]
 use anyhow::Error;
+#[cfg(not(feature = "native"))]
 use sgx_types::{sgx_launch_token_t, sgx_misc_attribute_t};
+#[cfg(not(feature = "native"))]
 use sgx_urts::SgxEnclave;
+#[cfg(not(feature = "native"))]
+use std::mem;
 
+#[cfg(not(feature = "native"))]
 pub type SharedSgxEnclave = Arc<SgxEnclave>;
+#[cfg(feature = "native")]
+pub type SharedSgxEnclave = Arc<NativeEnclave>;
 
 pub struct TEEJoinWorkerFactory {
     pub enclave: SharedSgxEnclave,
+    context: Arc<HostContext>,
 }
 
 impl TEEJoinWorkerFactory {
+    #[cfg(not(feature = "native"))]
     pub fn new(enclave_path: &Path) -> Result<Self> {
         println!("Init SGX enclave from {}.", enclave_path.display());
         let debug = 1;
@@ -26,8 +38,24 @@ impl TEEJoinWorkerFactory {
             &mut misc_attr,
         )
         .map_err(Error::msg)?;
+        let context = HostContext::register(enclave.geteid());
         let enclave = Arc::new(enclave);
-        Ok(Self { enclave })
+        Ok(Self { enclave, context })
+    }
+
+    /// Create an enclave in the host process. `enclave_path` is not loaded.
+    #[cfg(feature = "native")]
+    pub fn new(enclave_path: &Path) -> Result<Self> {
+        println!("Init native enclave in place of {}.", enclave_path.display());
+        let enclave = Arc::new(NativeEnclave::create());
+        let context = HostContext::register(enclave.geteid());
+        Ok(Self { enclave, context })
+    }
+
+    /// Host-side state accessed by the ocalls of this enclave.
+    #[inline]
+    pub fn context(&self) -> &Arc<HostContext> {
+        &self.context
     }
 
     pub fn use_enclave_in_the_same_dir() -> Result<Self> {
@@ -35,3 +63,9 @@ impl TEEJoinWorkerFactory {
         Self::new(&dir.join(env!("ENCLAVE_FILE_NAME")))
     }
 }
+
+impl Drop for TEEJoinWorkerFactory {
+    fn drop(&mut self) {
+        HostContext::unregister(self.enclave.geteid());
+    }
+}
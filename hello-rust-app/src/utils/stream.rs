@@ -0,0 +1,74 @@
+use super::*;
+use anyhow::anyhow;
+use hello_rust_core::util::L2Dist;
+use sgx_types::sgx_enclave_id_t;
+use std::{
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+};
+
+/// Options of streaming results from an enclave.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamConfig {
+    /// Size in bytes of the frames in which the enclave returns results, with one ocall per frame.
+    /// It must be positive, and the enclave caps it at 1 MiB.
+    pub frame_size: usize,
+    /// Number of results buffered on the host. The enclave blocks while the buffer is full.
+    pub channel_capacity: usize,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            frame_size: 64 * 1024,
+            channel_capacity: 1024,
+        }
+    }
+}
+
+/// Iterator over the results of an ecall, which runs on a background thread.
+///
+/// After the last result, it yields the error of the ecall if any. Dropping it stops the enclave
+/// at its next frame.
+pub struct ResultStream {
+    receiver: Receiver<L2Dist>,
+    handle: Option<JoinHandle<Result<()>>>,
+}
+
+impl ResultStream {
+    /// Run `ecall` on a new thread in the context of enclave `eid`. `ecall` is given the frame
+    /// size.
+    pub(crate) fn spawn<F>(eid: sgx_enclave_id_t, config: &StreamConfig, ecall: F) -> Result<Self>
+    where
+        F: FnOnce(usize) -> Result<()> + Send + 'static,
+    {
+        let ctx =
+            HostContext::get(eid).ok_or_else(|| anyhow!("no host context for enclave {eid}"))?;
+        let (sender, receiver) = mpsc::sync_channel(config.channel_capacity);
+        let frame_size = config.frame_size;
+        let handle = thread::spawn(move || {
+            let _guard = ctx.enter(sender);
+            ecall(frame_size)
+        });
+        Ok(Self {
+            receiver,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Iterator for ResultStream {
+    type Item = Result<L2Dist>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Ok(result) = self.receiver.recv() {
+            return Some(Ok(result));
+        }
+        // The sender is dropped, so the ecall has returned.
+        match self.handle.take()?.join() {
+            Ok(Ok(())) => None,
+            Ok(Err(err)) => Some(Err(err)),
+            Err(_) => Some(Err(anyhow!("ecall thread panicked"))),
+        }
+    }
+}
@@ -1,35 +1,234 @@
-use proc_macro2::TokenStream;
-use proc_macro_error::abort_call_site;
-use quote::{quote, quote_spanned};
+use proc_macro2::{Span, TokenStream};
+use proc_macro_error::{abort, abort_call_site, proc_macro_error};
+use quote::{format_ident, quote, quote_spanned};
+use syn::punctuated::Punctuated;
 use syn::spanned::Spanned;
-use syn::{parse_macro_input, Data, DeriveInput, Fields, Index};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DataEnum, DataStruct, DeriveInput, Expr,
+    Field, Fields, GenericParam, Generics, Index, LitStr, Token, WherePredicate,
+};
 
-#[proc_macro_derive(CMov)]
+/// Derive `CMov`.
+///
+/// Structs move every field with `CMov`, except fields marked with `#[cmov(skip)]` which are kept
+/// from the first operand. Every type parameter is bounded by `CMov` unless the bounds are given
+/// by `#[cmov(bound = "...")]`.
+///
+/// Enums should be `Copy`, and are moved as their fixed-size tagged representation in memory, so
+/// that the variant is not revealed.
+#[proc_macro_derive(CMov, attributes(cmov))]
+#[proc_macro_error]
 pub fn derive_cmov(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = input.ident;
+    let name = &input.ident;
+    let bound = container_bound(&input.attrs);
 
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let (body, default_bound) = match input.data {
+        Data::Struct(ref data) => (impl_struct(data), type_param_bound(&input.generics)),
+        Data::Enum(ref data) => {
+            let (_, ty_generics, _) = input.generics.split_for_impl();
+            let bound: WherePredicate = parse_quote!(#name #ty_generics: ::core::marker::Copy);
+            (impl_enum(data), vec![bound])
+        }
+        Data::Union(_) => abort_call_site!("union is not supported"),
+    };
 
-    let cnd_select = impl_cnd_select(&input.data);
-    let cnd_assign = impl_cnd_assign(&input.data);
-    let cnd_swap = impl_cnd_swap(&input.data);
+    let mut generics = input.generics.clone();
+    generics
+        .make_where_clause()
+        .predicates
+        .extend(bound.unwrap_or(default_bound));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let expanded = quote! {
         impl #impl_generics CMov for #name #ty_generics #where_clause {
-            #[inline]
-            fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
-                #cnd_select
+            #body
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// Parse `#[cmov(bound = "...")]` on the container.
+fn container_bound(attrs: &[Attribute]) -> Option<Vec<WherePredicate>> {
+    let mut bound = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("cmov")) {
+        let res = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("bound") {
+                let lit: LitStr = meta.value()?.parse()?;
+                let predicates = lit
+                    .parse_with(Punctuated::<WherePredicate, Token![,]>::parse_terminated)
+                    .map_err(|err| syn::Error::new(lit.span(), format!("invalid bound: {err}")))?;
+                bound = Some(predicates.into_iter().collect());
+                Ok(())
+            } else {
+                Err(meta.error("unknown cmov container attribute"))
             }
+        });
+        if let Err(err) = res {
+            abort!(err.span(), "{}", err);
+        }
+    }
+    bound
+}
+
+/// Parse `#[cmov(skip)]` on a field.
+fn field_skipped(field: &Field) -> bool {
+    let mut skip = false;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cmov"))
+    {
+        let res = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown cmov field attribute"))
+            }
+        });
+        if let Err(err) = res {
+            abort!(err.span(), "{}", err);
+        }
+    }
+    skip
+}
+
+/// `T: CMov` for every type parameter `T`.
+fn type_param_bound(generics: &Generics) -> Vec<WherePredicate> {
+    generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => {
+                let ident = &param.ident;
+                Some(parse_quote!(#ident: CMov))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn impl_struct(data: &DataStruct) -> TokenStream {
+    let fields: Vec<_> = data
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(i, f)| {
+            let member = match f.ident {
+                Some(ref name) => quote! { #name },
+                None => {
+                    let index = Index::from(i);
+                    quote! { #index }
+                }
+            };
+            (member, f.span(), field_skipped(f))
+        })
+        .collect();
+
+    let select = fields.iter().map(|(member, span, skip)| {
+        if *skip {
+            quote_spanned! { *span => ::core::clone::Clone::clone(&a.#member) }
+        } else {
+            quote_spanned! { *span => <_ as CMov>::cnd_select(&a.#member, &b.#member, choice) }
+        }
+    });
+    let cnd_select = match data.fields {
+        Fields::Named(_) => {
+            let members = fields.iter().map(|(member, _, _)| member);
+            quote! { Self { #(#members: #select),* } }
+        }
+        Fields::Unnamed(_) => quote! { Self(#(#select),*) },
+        Fields::Unit => quote! { Self },
+    };
+
+    let moved: Vec<_> = fields.iter().filter(|(_, _, skip)| !skip).collect();
+    let cnd_assign = moved.iter().map(|(member, span, _)| {
+        quote_spanned! { *span =>
+            self.#member.cnd_assign(&other.#member, choice);
+        }
+    });
+    let cnd_swap = moved.iter().map(|(member, span, _)| {
+        quote_spanned! { *span =>
+            <_ as CMov>::cnd_swap(&mut a.#member, &mut b.#member, choice);
+        }
+    });
+
+    quote! {
+        #[inline]
+        fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
+            #cnd_select
+        }
+
+        #[inline]
+        fn cnd_assign(&mut self, other: &Self, choice: bool) {
+            #(#cnd_assign)*
+        }
+
+        #[inline]
+        fn cnd_swap(a: &mut Self, b: &mut Self, choice: bool) {
+            #(#cnd_swap)*
+        }
+    }
+}
+
+fn impl_enum(data: &DataEnum) -> TokenStream {
+    for field in data.variants.iter().flat_map(|v| v.fields.iter()) {
+        if field_skipped(field) {
+            abort!(field.span(), "`#[cmov(skip)]` is not supported on enums");
+        }
+    }
+
+    quote! {
+        #[inline]
+        fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
+            ::hello_rust_core::cmov::__private::cnd_select_bytes(a, b, choice)
+        }
+
+        #[inline]
+        fn cnd_assign(&mut self, other: &Self, choice: bool) {
+            *self = <Self as CMov>::cnd_select(self, other, choice);
+        }
+    }
+}
+
+/// Derive `CtOrd`, which compares fields lexicographically in declaration order.
+#[proc_macro_derive(CtOrd)]
+#[proc_macro_error]
+pub fn derive_ct_ord(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = input.ident;
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let members = struct_members(&input.data);
+    let ct_lt = members.iter().map(|(member, span)| {
+        quote_spanned! { *span =>
+            lt |= eq & <_ as CtOrd>::ct_lt(&self.#member, &other.#member);
+            eq &= <_ as CtOrd>::ct_eq(&self.#member, &other.#member);
+        }
+    });
+    let ct_eq = members.iter().map(|(member, span)| {
+        quote_spanned! { *span =>
+            & <_ as CtOrd>::ct_eq(&self.#member, &other.#member)
+        }
+    });
 
+    let expanded = quote! {
+        impl #impl_generics CtOrd for #name #ty_generics #where_clause {
             #[inline]
-            fn cnd_assign(&mut self, other: &Self, choice: bool) {
-                #cnd_assign
+            #[allow(unused_assignments, unused_mut)]
+            fn ct_lt(&self, other: &Self) -> bool {
+                let mut lt = false;
+                let mut eq = true;
+                #(#ct_lt)*
+                lt
             }
 
             #[inline]
-            fn cnd_swap(a: &mut Self, b: &mut Self, choice: bool) {
-                #cnd_swap
+            fn ct_eq(&self, other: &Self) -> bool {
+                true #(#ct_eq)*
             }
         }
     };
@@ -37,100 +236,136 @@ pub fn derive_cmov(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     proc_macro::TokenStream::from(expanded)
 }
 
-fn impl_cnd_select(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let name = &f.ident;
-                    quote_spanned! { f.span() =>
-                        #name: <_ as CMov>::cnd_select(&a.#name, &b.#name, choice)
-                    }
-                });
-                quote! {
-                    Self { #(#recurse),* }
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let index = Index::from(i);
-                    quote_spanned! { f.span() =>
-                        <_ as CMov>::cnd_select(&a.#index, &b.#index, choice)
-                    }
-                });
-                quote! {
-                    Self(#(#recurse),*)
-                }
-            }
-            Fields::Unit => {
-                quote! { Self }
-            }
-        },
+/// Derive `FixedEncode`, which encodes the fields of a struct as a tuple in declaration order.
+///
+/// A `Vec` field must be marked with `#[fixed_encode(capacity = N)]`, and its elements must
+/// implement `Default`. Every type parameter is bounded by `FixedEncode`.
+#[proc_macro_derive(FixedEncode, attributes(fixed_encode))]
+#[proc_macro_error]
+pub fn derive_fixed_encode(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
         _ => abort_call_site!("only struct is supported"),
+    };
+    // Serde implements tuples of at most 16 elements.
+    if fields.len() > 16 {
+        abort!(fields.span(), "at most 16 fields are supported");
     }
-}
 
-fn impl_cnd_assign(data: &Data) -> TokenStream {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
-                    let name = &f.ident;
-                    quote_spanned! { f.span() =>
-                        self.#name.cnd_assign(&other.#name, choice);
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+    let private = quote! { ::hello_rust_core::cmov::__private };
+    let members = struct_members(&input.data);
+    let vars: Vec<_> = (0..fields.len())
+        .map(|i| format_ident!("__field{}", i))
+        .collect();
+    let (encoded, types): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .zip(members.iter())
+        .map(|(field, (member, span))| {
+            let ty = &field.ty;
+            match field_capacity(field) {
+                Some(capacity) => (
+                    quote_spanned! { *span => #private::FixedVec::<_, { #capacity }>(&self.#member) },
+                    quote_spanned! { *span => #private::FixedVec<#ty, { #capacity }> },
+                ),
+                None => (
+                    quote_spanned! { *span => #private::Fixed(&self.#member) },
+                    quote_spanned! { *span => #private::Fixed<#ty> },
+                ),
             }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let index = Index::from(i);
-                    quote_spanned! { f.span() =>
-                        self.#index.cnd_assign(&other.#index, choice);
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
+        })
+        .unzip();
+    let construct = match fields {
+        Fields::Named(_) => {
+            let names = members.iter().map(|(member, _)| member);
+            quote! { Self { #(#names: #vars.0),* } }
+        }
+        Fields::Unnamed(_) => quote! { Self(#(#vars.0),*) },
+        Fields::Unit => quote! { Self },
+    };
+
+    let mut generics = input.generics.clone();
+    let bound: Vec<WherePredicate> = input
+        .generics
+        .type_params()
+        .map(|param| {
+            let ident = &param.ident;
+            parse_quote!(#ident: FixedEncode)
+        })
+        .collect();
+    generics.make_where_clause().predicates.extend(bound);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics FixedEncode for #name #ty_generics #where_clause {
+            #[inline]
+            fn encode_fixed<__S: #private::serde::Serializer>(
+                &self,
+                serializer: __S,
+            ) -> ::core::result::Result<__S::Ok, __S::Error> {
+                #private::serde::Serialize::serialize(&(#(#encoded,)*), serializer)
             }
-            Fields::Unit => {
-                quote! {}
+
+            #[inline]
+            fn decode_fixed<'de, __D: #private::serde::Deserializer<'de>>(
+                deserializer: __D,
+            ) -> ::core::result::Result<Self, __D::Error> {
+                let (#(#vars,)*): (#(#types,)*) =
+                    #private::serde::Deserialize::deserialize(deserializer)?;
+                ::core::result::Result::Ok(#construct)
             }
-        },
-        _ => abort_call_site!("only struct is supported"),
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// Parse `#[fixed_encode(capacity = N)]` on a field.
+fn field_capacity(field: &Field) -> Option<Expr> {
+    let mut capacity = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("fixed_encode"))
+    {
+        let res = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("capacity") {
+                capacity = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown fixed_encode field attribute"))
+            }
+        });
+        if let Err(err) = res {
+            abort!(err.span(), "{}", err);
+        }
     }
+    capacity
 }
 
-fn impl_cnd_swap(data: &Data) -> TokenStream {
+/// Fields of a struct in declaration order.
+fn struct_members(data: &Data) -> Vec<(TokenStream, Span)> {
     match *data {
         Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => {
-                let recurse = fields.named.iter().map(|f| {
+            Fields::Named(ref fields) => fields
+                .named
+                .iter()
+                .map(|f| {
                     let name = &f.ident;
-                    quote_spanned! { f.span() =>
-                        <_ as CMov>::cnd_swap(&mut a.#name, &mut b.#name, choice);
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unnamed(ref fields) => {
-                let recurse = fields.unnamed.iter().enumerate().map(|(i, f)| {
+                    (quote! { #name }, f.span())
+                })
+                .collect(),
+            Fields::Unnamed(ref fields) => fields
+                .unnamed
+                .iter()
+                .enumerate()
+                .map(|(i, f)| {
                     let index = Index::from(i);
-                    quote_spanned! { f.span() =>
-                        <_ as CMov>::cnd_swap(&mut a.#index, &mut b.#index, choice);
-                    }
-                });
-                quote! {
-                    #(#recurse)*
-                }
-            }
-            Fields::Unit => {
-                quote! {}
-            }
+                    (quote! { #index }, f.span())
+                })
+                .collect(),
+            Fields::Unit => Vec::new(),
         },
         _ => abort_call_site!("only struct is supported"),
     }
@@ -0,0 +1,13 @@
+use hello_rust_core::cmov::CMov;
+
+#[derive(Clone, CMov)]
+enum Foo {
+    A(u32),
+    B,
+}
+
+fn assert_cmov<T: CMov>() {}
+
+fn main() {
+    assert_cmov::<Foo>();
+}
@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::FixedEncode;
+
+#[derive(FixedEncode)]
+enum Foo {
+    A(u32),
+    B(u64),
+}
+
+fn main() {}
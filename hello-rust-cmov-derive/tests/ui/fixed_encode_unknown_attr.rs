@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::FixedEncode;
+
+#[derive(FixedEncode)]
+struct Foo {
+    #[fixed_encode(len = 4)]
+    a: Vec<u32>,
+}
+
+fn main() {}
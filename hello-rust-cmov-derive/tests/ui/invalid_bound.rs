@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::CMov;
+
+#[derive(Clone, CMov)]
+#[cmov(bound = "T +")]
+struct Foo<T> {
+    a: T,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::CMov;
+
+#[derive(Clone, Copy, CMov)]
+enum Foo {
+    A(#[cmov(skip)] u32),
+    B,
+}
+
+fn main() {}
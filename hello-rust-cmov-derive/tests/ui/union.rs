@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::CMov;
+
+#[derive(Clone, Copy, CMov)]
+union Foo {
+    a: u32,
+    b: f32,
+}
+
+fn main() {}
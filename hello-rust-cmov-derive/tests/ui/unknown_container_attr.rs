@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::CMov;
+
+#[derive(Clone, CMov)]
+#[cmov(skip)]
+struct Foo {
+    a: u32,
+}
+
+fn main() {}
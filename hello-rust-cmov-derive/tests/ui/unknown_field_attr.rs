@@ -0,0 +1,9 @@
+use hello_rust_core::cmov::CMov;
+
+#[derive(Clone, CMov)]
+struct Foo {
+    #[cmov(ignore)]
+    a: u32,
+}
+
+fn main() {}
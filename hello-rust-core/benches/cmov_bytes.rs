@@ -0,0 +1,54 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use hello_rust_core::{
+    aligned::{Aligned, A64},
+    cmov::{self, portable},
+};
+
+type Backend = unsafe fn(bool, *const u8, *mut u8, usize);
+
+/// Backends supported by the CPU, with their names.
+fn backends() -> Vec<(&'static str, Backend)> {
+    let mut backends: Vec<(&'static str, Backend)> = vec![
+        ("portable_a1", portable::cmov_bytes_a1),
+        ("portable_a8", portable::cmov_bytes_a8),
+    ];
+    #[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+    {
+        backends.push(("cmovc_a1", cmov::cmov_bytes_a1));
+        backends.push(("cmovc_a8", cmov::cmov_bytes_a8));
+        backends.push(("sse2", cmov::cmov_bytes_sse2));
+        if is_x86_feature_detected!("avx2") {
+            backends.push(("avx2", cmov::cmov_bytes_avx2));
+        }
+        if is_x86_feature_detected!("avx512f") {
+            backends.push(("avx512", cmov::cmov_bytes_avx512));
+        }
+    }
+    backends
+}
+
+fn bench_cmov_bytes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cmov_bytes");
+    let mut size = 64;
+    while size <= 64 * 1024 {
+        let src = vec![Aligned::<A64, [u8; 64]>::new([0xa5; 64]); size / 64];
+        let mut dst = vec![Aligned::<A64, [u8; 64]>::new([0; 64]); size / 64];
+        group.throughput(Throughput::Bytes(size as u64));
+        for (name, f) in backends() {
+            group.bench_with_input(BenchmarkId::new(name, size), &size, |b, &size| {
+                let mut choice = false;
+                b.iter(|| {
+                    choice = !choice;
+                    let src = src.as_ptr() as *const u8;
+                    let dst = dst.as_mut_ptr() as *mut u8;
+                    unsafe { f(black_box(choice), src, dst, size) };
+                });
+            });
+        }
+        size *= 4;
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cmov_bytes);
+criterion_main!(benches);
@@ -0,0 +1,204 @@
+//! Oblivious group-by aggregation.
+//!
+//! Rows are sorted by key with bitonic sort, and then aggregated by a single linear scan with
+//! CMOV. The output has one slot per input row, where only the last row of each group holds the
+//! aggregate, so the number and sizes of groups are not revealed.
+
+use crate::{
+    cmov::{CMov, CndOption},
+    sort::bitonic_sort_by_key,
+};
+use alloc::vec::Vec;
+use core::ops::Add;
+
+/// Trait for aggregate functions over rows of type `T`.
+///
+/// Both methods are evaluated for every row, so they should not branch on the content of rows.
+pub trait Aggregator<T> {
+    type Output: CMov;
+
+    /// Aggregate of a group which only contains `row`.
+    fn init(&self, row: &T) -> Self::Output;
+
+    /// Add `row` into the aggregate `acc`.
+    fn merge(&self, acc: &Self::Output, row: &T) -> Self::Output;
+}
+
+/// Number of rows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Count;
+
+impl<T> Aggregator<T> for Count {
+    type Output = usize;
+
+    #[inline]
+    fn init(&self, _row: &T) -> usize {
+        1
+    }
+
+    #[inline]
+    fn merge(&self, acc: &usize, _row: &T) -> usize {
+        acc + 1
+    }
+}
+
+/// Sum of values extracted by `f`.
+#[derive(Debug, Clone, Copy)]
+pub struct Sum<F>(pub F);
+
+impl<T, V, F> Aggregator<T> for Sum<F>
+where
+    V: CMov + Add<Output = V>,
+    F: Fn(&T) -> V,
+{
+    type Output = V;
+
+    #[inline]
+    fn init(&self, row: &T) -> V {
+        (self.0)(row)
+    }
+
+    #[inline]
+    fn merge(&self, acc: &V, row: &T) -> V {
+        acc.clone() + (self.0)(row)
+    }
+}
+
+/// Minimum of values extracted by `f`.
+#[derive(Debug, Clone, Copy)]
+pub struct Min<F>(pub F);
+
+impl<T, V, F> Aggregator<T> for Min<F>
+where
+    V: CMov + Ord,
+    F: Fn(&T) -> V,
+{
+    type Output = V;
+
+    #[inline]
+    fn init(&self, row: &T) -> V {
+        (self.0)(row)
+    }
+
+    #[inline]
+    fn merge(&self, acc: &V, row: &T) -> V {
+        let v = (self.0)(row);
+        V::cnd_select(acc, &v, v < *acc)
+    }
+}
+
+/// Maximum of values extracted by `f`.
+#[derive(Debug, Clone, Copy)]
+pub struct Max<F>(pub F);
+
+impl<T, V, F> Aggregator<T> for Max<F>
+where
+    V: CMov + Ord,
+    F: Fn(&T) -> V,
+{
+    type Output = V;
+
+    #[inline]
+    fn init(&self, row: &T) -> V {
+        (self.0)(row)
+    }
+
+    #[inline]
+    fn merge(&self, acc: &V, row: &T) -> V {
+        let v = (self.0)(row);
+        V::cnd_select(acc, &v, v > *acc)
+    }
+}
+
+/// Compute two aggregates at once.
+impl<T, A, B> Aggregator<T> for (A, B)
+where
+    A: Aggregator<T>,
+    B: Aggregator<T>,
+{
+    type Output = (A::Output, B::Output);
+
+    #[inline]
+    fn init(&self, row: &T) -> Self::Output {
+        (self.0.init(row), self.1.init(row))
+    }
+
+    #[inline]
+    fn merge(&self, acc: &Self::Output, row: &T) -> Self::Output {
+        (self.0.merge(&acc.0, row), self.1.merge(&acc.1, row))
+    }
+}
+
+/// Group `rows` by the key extracted by `key` and aggregate each group with `agg`.
+///
+/// Return one slot per row, sorted by key. The slot of the last row in each group holds the key
+/// and the aggregate of the group, and the others are `None`. Use `compact_options` to move the
+/// groups to the front.
+pub fn group_by<T, K, F, A>(rows: &[T], mut key: F, agg: &A) -> Vec<CndOption<(K, A::Output)>>
+where
+    T: CMov,
+    K: CMov + Ord,
+    F: FnMut(&T) -> K,
+    A: Aggregator<T>,
+{
+    let mut rows: Vec<(K, T)> = rows.iter().map(|row| (key(row), row.clone())).collect();
+    bitonic_sort_by_key(&mut rows, |(k, _)| k.clone());
+
+    let mut ret = Vec::with_capacity(rows.len());
+    let mut acc: Option<A::Output> = None;
+    for i in 0..rows.len() {
+        let (k, row) = &rows[i];
+        let init = agg.init(row);
+        let next = match acc {
+            Some(ref acc) => {
+                let new_group = *k != rows[i - 1].0;
+                A::Output::cnd_select(&agg.merge(acc, row), &init, new_group)
+            }
+            None => init,
+        };
+        let last_of_group = i == rows.len() - 1 || *k != rows[i + 1].0;
+        ret.push(CndOption::new((k.clone(), next.clone()), last_of_group));
+        acc = Some(next);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
+
+    fn reveal<K: CMov, V: CMov>(res: Vec<CndOption<(K, V)>>) -> Vec<(K, V)> {
+        res.into_iter().filter_map(Option::from).collect()
+    }
+
+    proptest! {
+        #[test]
+        fn test_group_by(rows in prop::collection::vec((0u8..8, any::<u32>()), 0..100)) {
+            let mut expected: BTreeMap<u8, Vec<u32>> = BTreeMap::new();
+            for (k, v) in rows.iter() {
+                expected.entry(*k).or_default().push(*v);
+            }
+
+            let res = group_by(&rows, |r| r.0, &Count);
+            prop_assert_eq!(res.len(), rows.len());
+            let counts: Vec<_> = expected.iter().map(|(k, vs)| (*k, vs.len())).collect();
+            prop_assert_eq!(reveal(res), counts);
+
+            let res = group_by(&rows, |r| r.0, &Sum(|r: &(u8, u32)| r.1 as u64));
+            let sums: Vec<_> = expected
+                .iter()
+                .map(|(k, vs)| (*k, vs.iter().map(|v| *v as u64).sum::<u64>()))
+                .collect();
+            prop_assert_eq!(reveal(res), sums);
+
+            let res = group_by(&rows, |r| r.0, &(Min(|r: &(u8, u32)| r.1), Max(|r: &(u8, u32)| r.1)));
+            let min_max: Vec<_> = expected
+                .iter()
+                .map(|(k, vs)| (*k, (*vs.iter().min().unwrap(), *vs.iter().max().unwrap())))
+                .collect();
+            prop_assert_eq!(reveal(res), min_max);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use crate::ecall::{enclave_api, Result};
+use crate::util::{L2Dist, Point};
+use alloc::vec::Vec;
+
+/// Typed API of the enclave. The host calls it through `EnclaveApiClient`, and the enclave serves
+/// it with `dispatch_enclave_api`.
+#[enclave_api]
+pub trait EnclaveApi {
+    /// L2 distances of `pairs`, in order.
+    fn l2_dist(&self, pairs: Vec<(Point, Point)>) -> Result<Vec<L2Dist>>;
+}
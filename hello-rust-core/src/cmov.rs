@@ -24,15 +24,41 @@ pub trait CMov: Clone {
 
 mod impl_bool;
 mod impl_bytes;
+mod impl_f32_f64;
 mod impl_tuples;
 mod impl_u32_u64_usize;
 mod impl_u8_u16;
+mod impl_vec;
+pub mod portable;
 
-pub use impl_bytes::{cmov_bytes_a32, cmov_bytes_a64, cmov_bytes_a8};
+pub use impl_bytes::{
+    cmov_bytes_a1, cmov_bytes_a16, cmov_bytes_a32, cmov_bytes_a64, cmov_bytes_a8,
+};
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub use impl_bytes::{cmov_bytes_avx2, cmov_bytes_avx512, cmov_bytes_sse2};
 
 mod cnd_option;
 pub use cnd_option::*;
 
+mod cnd_result;
+pub use cnd_result::*;
+
+mod ct_ord;
+pub use ct_ord::*;
+
+mod fixed_encode;
+pub use fixed_encode::*;
+
+/// Items used by the derive macros.
+#[doc(hidden)]
+pub mod __private {
+    pub use super::fixed_encode::__private::*;
+    pub use super::impl_bytes::cnd_select_bytes;
+}
+
+mod slice;
+pub use slice::*;
+
 pub use hello_rust_cmov_derive::*;
 
 #[cfg(test)]
@@ -1,9 +1,18 @@
 use super::*;
 use core::{fmt, mem};
+use serde::{Deserialize, Serialize};
 
 /// Option<T> backed by CMOV.
-#[derive(Clone, CMov)]
+///
+/// It is serialized as the value followed by the flag even if it is none. The value is encoded by
+/// `FixedEncode`, so the encoded length does not depend on the presence.
+#[derive(Clone, CMov, Serialize, Deserialize)]
+#[serde(bound = "T: FixedEncode")]
 pub struct CndOption<T: CMov> {
+    #[serde(
+        serialize_with = "FixedEncode::encode_fixed",
+        deserialize_with = "FixedEncode::decode_fixed"
+    )]
     value: T,
     is_some: bool,
 }
@@ -117,6 +126,11 @@ impl<T: CMov> CndOption<T> {
         self.value
     }
 
+    #[inline]
+    pub fn as_ref_unchecked(&self) -> &T {
+        &self.value
+    }
+
     #[inline]
     pub fn take(&mut self) -> Self {
         let ret = self.clone();
@@ -242,7 +256,10 @@ impl<T: CMov> CndOption<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aligned::{Aligned, A8};
+    use crate::util::{compute_l2_distance, L2Dist, Point, MAX_FIXED_DIM};
     use core::fmt::Debug;
+    use proptest::prelude::*;
 
     fn assert_opt_eq<T: CMov + Debug + Eq>(a: CndOption<T>, b: Option<T>) {
         let a_opt: Option<T> = a.into();
@@ -339,4 +356,80 @@ mod tests {
         test_cnd_option(Some(1));
         test_cnd_option(None);
     }
+
+    #[test]
+    fn test_serde() {
+        type Bytes = Aligned<A8, [u8; 16]>;
+        let some = CndOption::new_some(Bytes::new([1; 16]));
+        let none = CndOption::<Bytes>::new_none();
+
+        let some_bytes = postcard::to_allocvec(&some).unwrap();
+        let none_bytes = postcard::to_allocvec(&none).unwrap();
+        assert_eq!(some_bytes.len(), none_bytes.len());
+        assert_eq!(
+            postcard::from_bytes::<CndOption<Bytes>>(&some_bytes).unwrap(),
+            some
+        );
+        assert_eq!(
+            postcard::from_bytes::<CndOption<Bytes>>(&none_bytes).unwrap(),
+            none
+        );
+
+        let json = serde_json::to_string(&CndOption::new_some(1u16)).unwrap();
+        assert_eq!(json, r#"{"value":[1,0],"is_some":true}"#);
+        assert_eq!(
+            serde_json::from_str::<CndOption<u16>>(&json).unwrap(),
+            CndOption::new_some(1)
+        );
+    }
+
+    #[test]
+    fn test_serde_fixed_len() {
+        let some = postcard::to_allocvec(&CndOption::new_some(u64::MAX)).unwrap();
+        let none = postcard::to_allocvec(&CndOption::<u64>::new_none()).unwrap();
+        assert_eq!(some.len(), none.len());
+
+        let some = postcard::to_allocvec(&CndOption::new_some(usize::MAX)).unwrap();
+        let none = postcard::to_allocvec(&CndOption::<usize>::new_none()).unwrap();
+        assert_eq!(some.len(), none.len());
+
+        let some = postcard::to_allocvec(&CndOption::new_some((u32::MAX, -1i64))).unwrap();
+        let none = postcard::to_allocvec(&CndOption::<(u32, i64)>::new_none()).unwrap();
+        assert_eq!(some.len(), none.len());
+    }
+
+    #[test]
+    fn test_serde_l2_dist() {
+        let point = |point_vec: Vec<f64>| Point { point_vec };
+        let l2 = compute_l2_distance(&point(vec![0.0, 0.0]), &point(vec![3.0, 4.0])).unwrap();
+        let some = CndOption::new_some(l2);
+        let none = CndOption::<L2Dist>::new_none();
+
+        let some_bytes = postcard::to_allocvec(&some).unwrap();
+        let none_bytes = postcard::to_allocvec(&none).unwrap();
+        assert_eq!(some_bytes.len(), none_bytes.len());
+
+        let output: CndOption<L2Dist> = postcard::from_bytes(&some_bytes).unwrap();
+        assert!(output.is_some());
+        let output = output.unwrap_unchecked();
+        assert_eq!(output.dist, 5.0);
+        assert_eq!(output.points[1].point_vec, vec![3.0, 4.0]);
+        let output: CndOption<L2Dist> = postcard::from_bytes(&none_bytes).unwrap();
+        assert!(output.is_none());
+
+        // A point of a dimension above the capacity can not be encoded.
+        let large = CndOption::new_some(point(vec![0.0; MAX_FIXED_DIM + 1]));
+        assert!(postcard::to_allocvec(&large).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn test_postcard_round_trip(value in any::<u64>(), is_some in prop::bool::ANY) {
+            let input = CndOption::new(value, is_some);
+            let bytes = postcard::to_allocvec(&input).unwrap();
+            let output: CndOption<u64> = postcard::from_bytes(&bytes).unwrap();
+            prop_assert_eq!(input.is_some(), output.is_some());
+            prop_assert_eq!(input.unwrap_unchecked(), output.unwrap_unchecked());
+        }
+    }
 }
@@ -0,0 +1,306 @@
+use super::*;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// Result<T, E> backed by CMOV.
+///
+/// Both the value and the error are always stored, and the methods evaluate both branches, so
+/// that whether it is ok is not revealed. Both are serialized by `FixedEncode` for the same reason.
+#[derive(Clone, CMov, Serialize, Deserialize)]
+#[serde(bound = "T: FixedEncode, E: FixedEncode")]
+pub struct CndResult<T: CMov, E: CMov> {
+    #[serde(
+        serialize_with = "FixedEncode::encode_fixed",
+        deserialize_with = "FixedEncode::decode_fixed"
+    )]
+    value: T,
+    #[serde(
+        serialize_with = "FixedEncode::encode_fixed",
+        deserialize_with = "FixedEncode::decode_fixed"
+    )]
+    error: E,
+    is_ok: bool,
+}
+
+impl<T: CMov + Copy, E: CMov + Copy> Copy for CndResult<T, E> {}
+
+impl<T: CMov + fmt::Debug, E: CMov + fmt::Debug> fmt::Debug for CndResult<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CndResult")
+            .field("value", &self.value)
+            .field("error", &self.error)
+            .field("is_ok", &self.is_ok)
+            .finish()
+    }
+}
+
+impl<T: CMov, E: CMov> From<CndResult<T, E>> for Result<T, E> {
+    #[inline]
+    fn from(input: CndResult<T, E>) -> Self {
+        if input.is_ok {
+            Ok(input.value)
+        } else {
+            Err(input.error)
+        }
+    }
+}
+
+impl<T: CMov + Default, E: CMov + Default> From<Result<T, E>> for CndResult<T, E> {
+    #[inline]
+    fn from(input: Result<T, E>) -> Self {
+        match input {
+            Ok(value) => Self::new_ok(value),
+            Err(error) => Self::new_err(error),
+        }
+    }
+}
+
+impl<T: CMov + PartialEq, E: CMov + PartialEq> PartialEq for CndResult<T, E> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.is_ok != other.is_ok {
+            return false;
+        }
+
+        if self.is_ok {
+            self.value == other.value
+        } else {
+            self.error == other.error
+        }
+    }
+}
+
+impl<T: CMov, E: CMov> CndResult<T, E> {
+    #[inline]
+    pub fn new(value: T, error: E, is_ok: bool) -> Self {
+        Self {
+            value,
+            error,
+            is_ok,
+        }
+    }
+
+    #[inline]
+    pub fn new_ok(value: T) -> Self
+    where
+        E: Default,
+    {
+        Self::new(value, E::default(), true)
+    }
+
+    #[inline]
+    pub fn new_err(error: E) -> Self
+    where
+        T: Default,
+    {
+        Self::new(T::default(), error, false)
+    }
+
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.is_ok
+    }
+
+    #[inline]
+    pub fn is_err(&self) -> bool {
+        !self.is_ok
+    }
+
+    #[inline]
+    pub fn ok(self) -> CndOption<T> {
+        CndOption::new(self.value, self.is_ok)
+    }
+
+    #[inline]
+    pub fn err(self) -> CndOption<E> {
+        CndOption::new(self.error, !self.is_ok)
+    }
+
+    #[inline]
+    pub fn unwrap(self) -> T {
+        assert!(self.is_ok);
+
+        self.value
+    }
+
+    #[inline]
+    pub fn unwrap_err(self) -> E {
+        assert!(!self.is_ok);
+
+        self.error
+    }
+
+    #[inline]
+    pub fn unwrap_or(self, default: T) -> T {
+        T::cnd_select(&default, &self.value, self.is_ok)
+    }
+
+    #[inline]
+    pub fn unwrap_or_else<F>(self, f: F) -> T
+    where
+        F: FnOnce(E) -> T,
+    {
+        let default = f(self.error.clone());
+        self.unwrap_or(default)
+    }
+
+    #[inline]
+    #[allow(clippy::or_fun_call)]
+    pub fn unwrap_or_default(self) -> T
+    where
+        T: Default,
+    {
+        self.unwrap_or(T::default())
+    }
+
+    #[inline]
+    pub fn unwrap_unchecked(self) -> T {
+        self.value
+    }
+
+    #[inline]
+    pub fn unwrap_err_unchecked(self) -> E {
+        self.error
+    }
+
+    #[inline]
+    pub fn map<U, F>(self, f: F) -> CndResult<U, E>
+    where
+        T: Default,
+        U: CMov,
+        F: FnOnce(T) -> U,
+    {
+        CndResult::new(
+            f(T::cnd_select(&T::default(), &self.value, self.is_ok)),
+            self.error,
+            self.is_ok,
+        )
+    }
+
+    #[inline]
+    pub fn map_err<G, F>(self, f: F) -> CndResult<T, G>
+    where
+        E: Default,
+        G: CMov,
+        F: FnOnce(E) -> G,
+    {
+        CndResult::new(
+            self.value,
+            f(E::cnd_select(&self.error, &E::default(), self.is_ok)),
+            self.is_ok,
+        )
+    }
+
+    #[inline]
+    pub fn and_then<U, F>(self, f: F) -> CndResult<U, E>
+    where
+        T: Default,
+        U: CMov,
+        F: FnOnce(T) -> CndResult<U, E>,
+    {
+        let tmp = f(T::cnd_select(&T::default(), &self.value, self.is_ok));
+        CndResult::new(
+            tmp.value,
+            E::cnd_select(&self.error, &tmp.error, self.is_ok),
+            self.is_ok & tmp.is_ok,
+        )
+    }
+
+    #[inline]
+    pub fn or_else<G, F>(self, f: F) -> CndResult<T, G>
+    where
+        E: Default,
+        G: CMov,
+        F: FnOnce(E) -> CndResult<T, G>,
+    {
+        let tmp = f(E::cnd_select(&self.error, &E::default(), self.is_ok));
+        CndResult::new(
+            T::cnd_select(&tmp.value, &self.value, self.is_ok),
+            tmp.error,
+            self.is_ok | tmp.is_ok,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Debug;
+    use proptest::prelude::*;
+
+    fn assert_res_eq<T: CMov + Debug + Eq, E: CMov + Debug + Eq>(
+        a: CndResult<T, E>,
+        b: Result<T, E>,
+    ) {
+        let a_res: Result<T, E> = a.into();
+        assert_eq!(a_res, b);
+    }
+
+    #[allow(clippy::all)]
+    fn test_cnd_result(res_input: Result<u64, u32>) {
+        let cnd_input: CndResult<u64, u32> = res_input.into();
+
+        assert_eq!(cnd_input.is_ok(), res_input.is_ok());
+        assert_eq!(cnd_input.is_err(), res_input.is_err());
+        assert_eq!(Option::from(cnd_input.ok()), res_input.ok());
+        assert_eq!(Option::from(cnd_input.err()), res_input.err());
+        assert_eq!(cnd_input.unwrap_or(1), res_input.unwrap_or(1));
+        assert_eq!(
+            cnd_input.unwrap_or_else(|e| e as u64 + 1),
+            res_input.unwrap_or_else(|e| e as u64 + 1),
+        );
+
+        assert_res_eq(cnd_input.map(|v| v + 1), res_input.map(|v| v + 1));
+        assert_res_eq(cnd_input.map_err(|e| e + 1), res_input.map_err(|e| e + 1));
+
+        assert_res_eq(
+            cnd_input.and_then(|v| CndResult::new_ok(v + 1)),
+            res_input.and_then(|v| Ok(v + 1)),
+        );
+        assert_res_eq(
+            cnd_input.and_then(|_| CndResult::<u64, u32>::new_err(2)),
+            res_input.and_then(|_| Err(2)),
+        );
+
+        assert_res_eq(
+            cnd_input.or_else(|e| CndResult::<u64, u32>::new_ok(e as u64 + 1)),
+            res_input.or_else(|e| Ok(e as u64 + 1)),
+        );
+        assert_res_eq(
+            cnd_input.or_else(|e| CndResult::<u64, u32>::new_err(e + 1)),
+            res_input.or_else(|e| Err(e + 1)),
+        );
+    }
+
+    #[test]
+    fn test() {
+        test_cnd_result(Ok(1));
+        test_cnd_result(Err(1));
+    }
+
+    #[test]
+    fn test_cnd_select() {
+        let ok = CndResult::<u64, u32>::new_ok(1);
+        let err = CndResult::<u64, u32>::new_err(2);
+        assert_eq!(CndResult::cnd_select(&ok, &err, false), ok);
+        assert_eq!(CndResult::cnd_select(&ok, &err, true), err);
+    }
+
+    #[test]
+    fn test_serde_fixed_len() {
+        let ok = postcard::to_allocvec(&CndResult::<u64, i32>::new_ok(u64::MAX)).unwrap();
+        let err = postcard::to_allocvec(&CndResult::<u64, i32>::new_err(i32::MIN)).unwrap();
+        assert_eq!(ok.len(), err.len());
+    }
+
+    proptest! {
+        #[test]
+        fn test_postcard_round_trip(value in any::<u64>(), error in any::<u32>(), is_ok in prop::bool::ANY) {
+            let input = CndResult::new(value, error, is_ok);
+            let bytes = postcard::to_allocvec(&input).unwrap();
+            let output: CndResult<u64, u32> = postcard::from_bytes(&bytes).unwrap();
+            prop_assert_eq!(output.is_ok(), is_ok);
+            prop_assert_eq!(output.unwrap_unchecked(), value);
+            prop_assert_eq!(output.unwrap_err_unchecked(), error);
+        }
+    }
+}
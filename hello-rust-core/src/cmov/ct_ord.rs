@@ -0,0 +1,172 @@
+use crate::aligned::{Aligned, Alignment};
+
+/// Trait for constant-time comparison, which does not branch on the compared values.
+///
+/// Composite types compare their fields lexicographically, and every field is always compared.
+pub trait CtOrd {
+    /// Return true if self < other.
+    fn ct_lt(&self, other: &Self) -> bool;
+
+    /// Return true if self == other.
+    fn ct_eq(&self, other: &Self) -> bool;
+
+    /// Return true if self > other.
+    #[inline]
+    fn ct_gt(&self, other: &Self) -> bool {
+        other.ct_lt(self)
+    }
+
+    /// Return true if self <= other.
+    #[inline]
+    fn ct_le(&self, other: &Self) -> bool {
+        !other.ct_lt(self)
+    }
+
+    /// Return true if self >= other.
+    #[inline]
+    fn ct_ge(&self, other: &Self) -> bool {
+        !self.ct_lt(other)
+    }
+}
+
+// From Hacker's Delight 2-12, the sign bit of
+// (!a & b) | (!(a ^ b) & (a - b))
+// is the borrow of a - b, i.e. whether a < b.
+macro_rules! impl_ct_ord_for_primitives {
+    ($x:ty, $signed_x:ty) => {
+        impl CtOrd for $x {
+            #[inline]
+            fn ct_lt(&self, other: &Self) -> bool {
+                let (a, b) = (*self, *other);
+                let borrow = (!a & b) | (!(a ^ b) & a.wrapping_sub(b));
+                (borrow >> (<$x>::BITS - 1)) as u8 != 0
+            }
+
+            #[inline]
+            fn ct_eq(&self, other: &Self) -> bool {
+                let x = *self ^ *other;
+                ((x | x.wrapping_neg()) >> (<$x>::BITS - 1)) as u8 == 0
+            }
+        }
+
+        // Flip the sign bit to map signed integers to unsigned ones with the same order.
+        impl CtOrd for $signed_x {
+            #[inline]
+            fn ct_lt(&self, other: &Self) -> bool {
+                let sign = 1 << (<$x>::BITS - 1);
+                (*self as $x ^ sign).ct_lt(&(*other as $x ^ sign))
+            }
+
+            #[inline]
+            fn ct_eq(&self, other: &Self) -> bool {
+                (*self as $x).ct_eq(&(*other as $x))
+            }
+        }
+    };
+}
+
+impl_ct_ord_for_primitives!(u8, i8);
+impl_ct_ord_for_primitives!(u16, i16);
+impl_ct_ord_for_primitives!(u32, i32);
+impl_ct_ord_for_primitives!(u64, i64);
+impl_ct_ord_for_primitives!(u128, i128);
+impl_ct_ord_for_primitives!(usize, isize);
+
+impl CtOrd for bool {
+    #[inline]
+    fn ct_lt(&self, other: &Self) -> bool {
+        !*self & *other
+    }
+
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        !(*self ^ *other)
+    }
+}
+
+impl CtOrd for () {
+    #[inline]
+    fn ct_lt(&self, _other: &Self) -> bool {
+        false
+    }
+
+    #[inline]
+    fn ct_eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T: CtOrd, const N: usize> CtOrd for [T; N] {
+    #[inline]
+    fn ct_lt(&self, other: &Self) -> bool {
+        let mut lt = false;
+        let mut eq = true;
+        for (a, b) in self.iter().zip(other) {
+            lt |= eq & a.ct_lt(b);
+            eq &= a.ct_eq(b);
+        }
+        lt
+    }
+
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        let mut eq = true;
+        for (a, b) in self.iter().zip(other) {
+            eq &= a.ct_eq(b);
+        }
+        eq
+    }
+}
+
+impl<A: Alignment, T: CtOrd> CtOrd for Aligned<A, T> {
+    #[inline]
+    fn ct_lt(&self, other: &Self) -> bool {
+        (**self).ct_lt(other)
+    }
+
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        (**self).ct_eq(other)
+    }
+}
+
+macro_rules! impl_tuple {
+    ($($index:tt $name:ident)+) => {
+        impl<$($name),+> CtOrd for ($($name,)+)
+        where
+            $($name: CtOrd),+
+        {
+            #[inline]
+            #[allow(unused_assignments)]
+            fn ct_lt(&self, other: &Self) -> bool {
+                let mut lt = false;
+                let mut eq = true;
+                $(
+                    lt |= eq & self.$index.ct_lt(&other.$index);
+                    eq &= self.$index.ct_eq(&other.$index);
+                )+
+                lt
+            }
+
+            #[inline]
+            fn ct_eq(&self, other: &Self) -> bool {
+                true $(& self.$index.ct_eq(&other.$index))+
+            }
+        }
+    };
+}
+
+impl_tuple!(0 T0);
+impl_tuple!(0 T0 1 T1);
+impl_tuple!(0 T0 1 T1 2 T2);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12);
+impl_tuple!(0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7 8 T8 9 T9 10 T10 11 T11 12 T12 13 T13);
@@ -0,0 +1,226 @@
+use crate::aligned::{Aligned, Alignment};
+use alloc::vec::Vec;
+use core::{fmt, marker::PhantomData};
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::{self, SerializeTuple},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Types with a serde encoding of the same length for every value.
+///
+/// `CndOption` and `CndResult` encode their fields by it, so that the encoded length does not
+/// reveal which field is meaningful. Integers are encoded as little-endian bytes, since postcard
+/// encodes them as varint.
+///
+/// Structs implement it by `derive(FixedEncode)`, which encodes the fields as a tuple. A `Vec`
+/// field needs `#[fixed_encode(capacity = N)]`, and is encoded as its length followed by `N`
+/// elements, padded by `Default`. Encoding fails if it is longer than `N`. `Vec` does not
+/// implement the trait itself, since its length is only bounded per field.
+pub trait FixedEncode: Sized {
+    fn encode_fixed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    fn decode_fixed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+}
+
+macro_rules! impl_fixed_encode_int {
+    ($($t: ty => $repr: ty),*) => {
+        $(
+            impl FixedEncode for $t {
+                #[inline]
+                fn encode_fixed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    (*self as $repr).to_le_bytes().encode_fixed(serializer)
+                }
+
+                #[inline]
+                fn decode_fixed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let bytes = FixedEncode::decode_fixed(deserializer)?;
+                    <$t>::try_from(<$repr>::from_le_bytes(bytes)).map_err(de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+// `usize` and `isize` are encoded as 64 bits, so that both sides of the enclave agree.
+impl_fixed_encode_int!(
+    u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => u64,
+    i8 => i8, i16 => i16, i32 => i32, i64 => i64, i128 => i128, isize => i64
+);
+
+// Postcard encodes these with a fixed size already.
+macro_rules! impl_fixed_encode_serde {
+    ($($t: ty),*) => {
+        $(
+            impl FixedEncode for $t {
+                #[inline]
+                fn encode_fixed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    self.serialize(serializer)
+                }
+
+                #[inline]
+                fn decode_fixed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    Self::deserialize(deserializer)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_encode_serde!(u8, bool, f32, f64, ());
+
+impl<const N: usize> FixedEncode for [u8; N] {
+    fn encode_fixed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for byte in self {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+
+    fn decode_fixed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor<const N: usize>;
+
+        impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+            type Value = [u8; N];
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an array of {N} bytes")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut bytes = [0; N];
+                for (i, byte) in bytes.iter_mut().enumerate() {
+                    *byte = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+                }
+                Ok(bytes)
+            }
+        }
+
+        deserializer.deserialize_tuple(N, BytesVisitor::<N>)
+    }
+}
+
+impl<A: Alignment, T: FixedEncode> FixedEncode for Aligned<A, T> {
+    #[inline]
+    fn encode_fixed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).encode_fixed(serializer)
+    }
+
+    #[inline]
+    fn decode_fixed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::decode_fixed(deserializer).map(Self::new)
+    }
+}
+
+macro_rules! impl_fixed_encode_tuple {
+    ($(($($name: ident $index: tt),+)),*) => {
+        $(
+            impl<$($name: FixedEncode),+> FixedEncode for ($($name,)+) {
+                #[inline]
+                fn encode_fixed<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    ($(__private::Fixed(&self.$index),)+).serialize(serializer)
+                }
+
+                #[inline]
+                fn decode_fixed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let tuple: ($(__private::Fixed<$name>,)+) = Deserialize::deserialize(deserializer)?;
+                    Ok(($(tuple.$index.0,)+))
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_encode_tuple!(
+    (T0 0),
+    (T0 0, T1 1),
+    (T0 0, T1 1, T2 2),
+    (T0 0, T1 1, T2 2, T3 3)
+);
+
+/// Items used by `derive(FixedEncode)`, which are re-exported by `cmov::__private`.
+pub mod __private {
+    use super::*;
+
+    pub use serde;
+
+    /// Encode a field by `FixedEncode`. It serializes `Fixed<&T>` and deserializes `Fixed<T>`.
+    pub struct Fixed<T>(pub T);
+
+    impl<T: FixedEncode> Serialize for Fixed<&T> {
+        #[inline]
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.0.encode_fixed(serializer)
+        }
+    }
+
+    impl<'de, T: FixedEncode> Deserialize<'de> for Fixed<T> {
+        #[inline]
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::decode_fixed(deserializer).map(Self)
+        }
+    }
+
+    /// Encode a `Vec` field of capacity `N`. It serializes `FixedVec<&Vec<T>, N>` and
+    /// deserializes `FixedVec<Vec<T>, N>`.
+    pub struct FixedVec<T, const N: usize>(pub T);
+
+    impl<T: FixedEncode + Default, const N: usize> Serialize for FixedVec<&Vec<T>, N> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let vec = self.0;
+            if vec.len() > N {
+                return Err(ser::Error::custom(format_args!(
+                    "length {} exceeds the capacity {N}",
+                    vec.len()
+                )));
+            }
+            let padding = T::default();
+            let mut tuple = serializer.serialize_tuple(N + 1)?;
+            tuple.serialize_element(&Fixed(&vec.len()))?;
+            for i in 0..N {
+                tuple.serialize_element(&Fixed(vec.get(i).unwrap_or(&padding)))?;
+            }
+            tuple.end()
+        }
+    }
+
+    impl<'de, T: FixedEncode, const N: usize> Deserialize<'de> for FixedVec<Vec<T>, N> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct VecVisitor<T, const N: usize>(PhantomData<T>);
+
+            impl<'de, T: FixedEncode, const N: usize> Visitor<'de> for VecVisitor<T, N> {
+                type Value = Vec<T>;
+
+                fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "a length followed by {N} elements")
+                }
+
+                fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                    let Fixed(len): Fixed<usize> = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                    if len > N {
+                        return Err(de::Error::invalid_length(len, &self));
+                    }
+                    let mut vec = Vec::with_capacity(len);
+                    for i in 0..N {
+                        let Fixed(item): Fixed<T> = seq
+                            .next_element()?
+                            .ok_or_else(|| de::Error::invalid_length(i + 1, &self))?;
+                        if i < len {
+                            vec.push(item);
+                        }
+                    }
+                    Ok(vec)
+                }
+            }
+
+            deserializer
+                .deserialize_tuple(N + 1, VecVisitor::<T, N>(PhantomData))
+                .map(Self)
+        }
+    }
+}
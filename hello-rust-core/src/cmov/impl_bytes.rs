@@ -1,7 +1,40 @@
 use super::CMov;
 use crate::aligned::{Aligned, AlignedBox, A16, A32, A64, A8};
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
 use core::arch::asm;
-use core::mem;
+use core::mem::{self, MaybeUninit};
+
+#[cfg(any(not(target_arch = "x86_64"), feature = "portable-cmov"))]
+pub use super::portable::{cmov_bytes_a1, cmov_bytes_a8};
+
+/// CMov bytes array without alignment requirement.
+///
+/// # Safety
+///
+/// count should be non-zero. src and dst are valid for count bytes.
+#[inline(always)]
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub unsafe fn cmov_bytes_a1(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+    debug_assert!(count > 0);
+    let cnd = cnd as u64;
+    asm!(
+        "neg {0}",
+        "2:",
+            "movzx {4:e}, byte ptr [{3} + {1} - 1]",
+            "movzx {5:e}, byte ptr [{2} + {1} - 1]",
+            "cmovc {4:e}, {5:e}",
+            "mov byte ptr [{3} + {1} - 1], {4:l}",
+            "dec {1}",
+            "jnz 2b",
+        inout(reg) cnd => _,
+        inout(reg) count => _,
+        in(reg) src,
+        in(reg) dst,
+        out(reg) _,
+        out(reg) _,
+        options(nostack),
+    );
+}
 
 /// CMov bytes array which is 8-bytes aligned.
 ///
@@ -9,6 +42,7 @@ use core::mem;
 ///
 /// count should be non-zero. src and dst are properly aligned.
 #[inline(always)]
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
 pub unsafe fn cmov_bytes_a8(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
     debug_assert!(count > 0);
     debug_assert_eq!(count % 8, 0);
@@ -32,14 +66,56 @@ pub unsafe fn cmov_bytes_a8(cnd: bool, src: *const u8, dst: *mut u8, count: usiz
     );
 }
 
-/// CMov bytes array which is 32-bytes aligned.
+/// CMov bytes array which is 16-bytes aligned with SSE2.
+///
+/// Both src and dst are always read and dst is always written, and the bytes are selected by
+/// masking.
 ///
 /// # Safety
 ///
 /// count should be non-zero. src and dst are properly aligned.
 #[inline(always)]
-#[cfg(target_feature = "avx2")]
-pub unsafe fn cmov_bytes_a32(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub unsafe fn cmov_bytes_sse2(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+    debug_assert!(count > 0);
+    debug_assert_eq!(count % 16, 0);
+    debug_assert_eq!(src.align_offset(16), 0);
+    debug_assert_eq!(dst.align_offset(16), 0);
+    let cnd = cnd as u64;
+    asm!(
+        "neg {0}",
+        "movq xmm0, {0}",
+        "punpcklqdq xmm0, xmm0",
+        "mov {0}, {3}",
+        "2:",
+            "movdqa xmm1, xmmword ptr [{2} + {0} - 16]",
+            "movdqa xmm2, xmmword ptr [{1} + {0} - 16]",
+            "pxor xmm2, xmm1",
+            "pand xmm2, xmm0",
+            "pxor xmm1, xmm2",
+            "movdqa xmmword ptr [{2} + {0} - 16], xmm1",
+            "sub {0}, 16",
+            "jnz 2b",
+        inout(reg) cnd => _,
+        in(reg) src,
+        in(reg) dst,
+        in(reg) count,
+        out("xmm0") _,
+        out("xmm1") _,
+        out("xmm2") _,
+        options(nostack),
+    );
+}
+
+/// CMov bytes array which is 32-bytes aligned with AVX2.
+///
+/// # Safety
+///
+/// count should be non-zero. src and dst are properly aligned. The CPU supports AVX2.
+#[inline]
+#[target_feature(enable = "avx2")]
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub unsafe fn cmov_bytes_avx2(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
     debug_assert!(count > 0);
     debug_assert_eq!(count % 32, 0);
     debug_assert_eq!(src.align_offset(32), 0);
@@ -59,29 +135,24 @@ pub unsafe fn cmov_bytes_a32(cnd: bool, src: *const u8, dst: *mut u8, count: usi
         in(reg) src,
         in(reg) dst,
         in(reg) count,
+        out("ymm1") _,
+        out("ymm2") _,
         options(nostack),
     );
 }
 
-/// CMov bytes array which is 32-bytes aligned.
-///
-/// # Safety
+/// CMov bytes array which is 64-bytes aligned with AVX-512.
 ///
-/// count should be non-zero. src and dst are properly aligned.
-#[inline(always)]
-#[cfg(not(target_feature = "avx2"))]
-pub unsafe fn cmov_bytes_a32(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
-    cmov_bytes_a8(cnd, src, dst, count)
-}
-
-/// CMov bytes array which is 64-bytes aligned.
+/// Both src and dst are always read and dst is always written, and the bytes are selected by a
+/// masked move between registers.
 ///
 /// # Safety
 ///
-/// count should be non-zero. src and dst are properly aligned.
-#[inline(always)]
-#[cfg(target_feature = "avx2")]
-pub unsafe fn cmov_bytes_a64(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+/// count should be non-zero. src and dst are properly aligned. The CPU supports AVX-512F.
+#[inline]
+#[target_feature(enable = "avx512f")]
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub unsafe fn cmov_bytes_avx512(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
     debug_assert!(count > 0);
     debug_assert_eq!(count % 64, 0);
     debug_assert_eq!(src.align_offset(64), 0);
@@ -89,33 +160,118 @@ pub unsafe fn cmov_bytes_a64(cnd: bool, src: *const u8, dst: *mut u8, count: usi
     let cnd = cnd as u64;
     asm!(
         "neg {0}",
-        "vmovq xmm2, {0}",
-        "vbroadcastsd ymm1, xmm2",
+        "kmovw k1, {0:e}",
         "mov {0}, {3}",
         "2:",
-            "vmovdqa ymm2, ymmword ptr [{1} + {0} - 64]",
-            "vpmaskmovq ymmword ptr [{2} + {0} - 64], ymm1, ymm2",
-            "vmovdqa ymm3, ymmword ptr [{1} + {0} - 32]",
-            "vpmaskmovq ymmword ptr [{2} + {0} - 32], ymm1, ymm3",
+            "vmovdqa64 zmm1, zmmword ptr [{2} + {0} - 64]",
+            "vmovdqa64 zmm2, zmmword ptr [{1} + {0} - 64]",
+            "vmovdqa32 zmm1 {{k1}}, zmm2",
+            "vmovdqa64 zmmword ptr [{2} + {0} - 64], zmm1",
             "sub {0}, 64",
             "jnz 2b",
         inout(reg) cnd => _,
         in(reg) src,
         in(reg) dst,
         in(reg) count,
+        out("k1") _,
+        out("zmm1") _,
+        out("zmm2") _,
         options(nostack),
     );
 }
 
+/// CMov bytes array which is 16-bytes aligned.
+///
+/// # Safety
+///
+/// count should be non-zero. src and dst are properly aligned.
+#[inline(always)]
+pub unsafe fn cmov_bytes_a16(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+    #[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+    cmov_bytes_sse2(cnd, src, dst, count);
+    #[cfg(any(not(target_arch = "x86_64"), feature = "portable-cmov"))]
+    cmov_bytes_a8(cnd, src, dst, count);
+}
+
+/// CMov bytes array which is 32-bytes aligned.
+///
+/// Use AVX2 if it is enabled at compile time.
+///
+/// # Safety
+///
+/// count should be non-zero. src and dst are properly aligned.
+#[inline(always)]
+pub unsafe fn cmov_bytes_a32(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+    #[cfg(all(
+        target_arch = "x86_64",
+        not(feature = "portable-cmov"),
+        target_feature = "avx2"
+    ))]
+    cmov_bytes_avx2(cnd, src, dst, count);
+    #[cfg(not(all(
+        target_arch = "x86_64",
+        not(feature = "portable-cmov"),
+        target_feature = "avx2"
+    )))]
+    cmov_bytes_a16(cnd, src, dst, count);
+}
+
 /// CMov bytes array which is 64-bytes aligned.
 ///
+/// Use AVX-512 or AVX2 if it is enabled at compile time.
+///
 /// # Safety
 ///
 /// count should be non-zero. src and dst are properly aligned.
 #[inline(always)]
-#[cfg(not(target_feature = "avx2"))]
 pub unsafe fn cmov_bytes_a64(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
-    cmov_bytes_a8(cnd, src, dst, count)
+    #[cfg(all(
+        target_arch = "x86_64",
+        not(feature = "portable-cmov"),
+        target_feature = "avx512f"
+    ))]
+    cmov_bytes_avx512(cnd, src, dst, count);
+    #[cfg(not(all(
+        target_arch = "x86_64",
+        not(feature = "portable-cmov"),
+        target_feature = "avx512f"
+    )))]
+    cmov_bytes_a32(cnd, src, dst, count);
+}
+
+/// Select `a` if choice is 0 (false), or `b` if choice is 1 (true), by their raw bytes including
+/// the tag of enums. It is used by `derive(CMov)` on enums.
+///
+/// Padding and the bytes of inactive variants are uninitialized and must not be read as `u8`, so
+/// the copies are first passed to an empty `asm` block, which the compiler must assume writes to
+/// them.
+#[doc(hidden)]
+#[inline]
+pub fn cnd_select_bytes<T: Copy>(a: &T, b: &T, choice: bool) -> T {
+    let count = mem::size_of::<T>();
+    if count == 0 {
+        return *a;
+    }
+    let mut out = MaybeUninit::new(*a);
+    let mut src = MaybeUninit::new(*b);
+    unsafe {
+        freeze(out.as_mut_ptr() as *mut u8);
+        freeze(src.as_mut_ptr() as *mut u8);
+        cmov_bytes_a1(
+            choice,
+            src.as_ptr() as *const u8,
+            out.as_mut_ptr() as *mut u8,
+            count,
+        );
+        // SAFETY: all bytes of `out` are copied from either `a` or `b`.
+        out.assume_init()
+    }
+}
+
+/// Make the memory at `ptr` opaque to the compiler, so that its bytes are initialized afterwards.
+#[inline(always)]
+unsafe fn freeze(ptr: *mut u8) {
+    core::arch::asm!("/* {0} */", in(reg) ptr, options(nostack, preserves_flags));
 }
 
 impl<T: Copy> CMov for Aligned<A8, T> {
@@ -154,7 +310,7 @@ impl<T: Copy> CMov for Aligned<A16, T> {
             let src = other as *const Self as *const u8;
             let dst = self as *mut Self as *mut u8;
             unsafe {
-                cmov_bytes_a8(choice, src, dst, count);
+                cmov_bytes_a16(choice, src, dst, count);
             }
         }
     }
@@ -240,7 +396,7 @@ impl<T: Copy> CMov for AlignedBox<A16, [T]> {
             let src = other.as_ptr() as *const T as *const u8;
             let dst = self.as_mut_ptr() as *mut T as *mut u8;
             unsafe {
-                cmov_bytes_a8(choice, src, dst, count);
+                cmov_bytes_a16(choice, src, dst, count);
             }
         }
     }
@@ -316,11 +472,17 @@ mod tests {
 
     macro_rules! test_cmov_bytes {
         ($name: ident, $func: ident) => {
+            test_cmov_bytes!($name, $func, true);
+        };
+        ($name: ident, $func: ident, $supported: expr) => {
             proptest! {
                 #![proptest_config(ProptestConfig { fork: true, ..Default::default() })]
 
                 #[test]
                 fn $name((size, a, b) in arb_two_vecs(1..10)) {
+                    if !$supported {
+                        return Ok(());
+                    }
                     let mut res = a.clone();
                     unsafe {
                         $func(false, a.as_ptr() as *const u8, res.as_mut_ptr() as *mut u8, size);
@@ -338,6 +500,14 @@ mod tests {
     }
 
     test_cmov_bytes!(test_a8, cmov_bytes_a8);
+    test_cmov_bytes!(test_a16, cmov_bytes_a16);
     test_cmov_bytes!(test_a32, cmov_bytes_a32);
     test_cmov_bytes!(test_a64, cmov_bytes_a64);
+
+    #[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+    test_cmov_bytes!(test_sse2, cmov_bytes_sse2);
+    #[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+    test_cmov_bytes!(test_avx2, cmov_bytes_avx2, is_x86_feature_detected!("avx2"));
+    #[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+    test_cmov_bytes!(test_avx512, cmov_bytes_avx512, is_x86_feature_detected!("avx512f"));
 }
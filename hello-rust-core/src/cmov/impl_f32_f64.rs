@@ -0,0 +1,16 @@
+use super::CMov;
+
+// Select the bit patterns, so that NaN payloads and signed zeros are preserved.
+impl CMov for f32 {
+    #[inline]
+    fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
+        f32::from_bits(u32::cnd_select(&a.to_bits(), &b.to_bits(), choice))
+    }
+}
+
+impl CMov for f64 {
+    #[inline]
+    fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
+        f64::from_bits(u64::cnd_select(&a.to_bits(), &b.to_bits(), choice))
+    }
+}
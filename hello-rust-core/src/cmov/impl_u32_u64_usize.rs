@@ -1,8 +1,13 @@
 use super::CMov;
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
 use core::arch::asm;
 
+#[cfg(any(not(target_arch = "x86_64"), feature = "portable-cmov"))]
+use super::portable::{cmov_u32, cmov_u64};
+
 #[inline(always)]
-fn cmov_u32(cnd: bool, a: u32, b: u32) -> u32 {
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub(super) fn cmov_u32(cnd: bool, a: u32, b: u32) -> u32 {
     let mut res = a;
     let cnd = cnd as u64;
     unsafe {
@@ -19,7 +24,8 @@ fn cmov_u32(cnd: bool, a: u32, b: u32) -> u32 {
 }
 
 #[inline(always)]
-fn cmov_u64(cnd: bool, a: u64, b: u64) -> u64 {
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+pub(super) fn cmov_u64(cnd: bool, a: u64, b: u64) -> u64 {
     let mut res = a;
     let cnd = cnd as u64;
     unsafe {
@@ -0,0 +1,28 @@
+use super::CMov;
+use alloc::vec::Vec;
+
+/// CMov element-wise. Panic if the lengths differ.
+impl<T: CMov> CMov for Vec<T> {
+    #[inline]
+    fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
+        let mut out = a.clone();
+        out.cnd_assign(b, choice);
+        out
+    }
+
+    #[inline]
+    fn cnd_assign(&mut self, other: &Self, choice: bool) {
+        assert_eq!(self.len(), other.len());
+        for (a, b) in self.iter_mut().zip(other) {
+            a.cnd_assign(b, choice);
+        }
+    }
+
+    #[inline]
+    fn cnd_swap(a: &mut Self, b: &mut Self, choice: bool) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+            T::cnd_swap(a, b, choice);
+        }
+    }
+}
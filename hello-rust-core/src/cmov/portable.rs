@@ -0,0 +1,66 @@
+//! Portable CMOV backend by mask arithmetic, used on targets other than x86-64 or with the
+//! `portable-cmov` feature. It is always built so that it can be compared with the asm backend.
+//!
+//! The mask is passed through `black_box`, so that the compiler does not know it is either 0 or
+//! !0 and cannot turn the selection back into a branch.
+
+use core::hint::black_box;
+
+#[inline(always)]
+fn mask_u8(cnd: bool) -> u8 {
+    black_box(cnd as u8).wrapping_neg()
+}
+
+#[inline(always)]
+fn mask_u64(cnd: bool) -> u64 {
+    black_box(cnd as u64).wrapping_neg()
+}
+
+#[inline(always)]
+pub fn cmov_u32(cnd: bool, a: u32, b: u32) -> u32 {
+    let mask = mask_u64(cnd) as u32;
+    a ^ (mask & (a ^ b))
+}
+
+#[inline(always)]
+pub fn cmov_u64(cnd: bool, a: u64, b: u64) -> u64 {
+    let mask = mask_u64(cnd);
+    a ^ (mask & (a ^ b))
+}
+
+/// CMov bytes array without alignment requirement.
+///
+/// # Safety
+///
+/// count should be non-zero. src and dst are valid for count bytes.
+#[inline(always)]
+pub unsafe fn cmov_bytes_a1(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+    debug_assert!(count > 0);
+    let mask = mask_u8(cnd);
+    for i in 0..count {
+        let a = dst.add(i).read();
+        let b = src.add(i).read();
+        dst.add(i).write(a ^ (mask & (a ^ b)));
+    }
+}
+
+/// CMov bytes array which is 8-bytes aligned.
+///
+/// # Safety
+///
+/// count should be non-zero. src and dst are properly aligned.
+#[inline(always)]
+pub unsafe fn cmov_bytes_a8(cnd: bool, src: *const u8, dst: *mut u8, count: usize) {
+    debug_assert!(count > 0);
+    debug_assert_eq!(count % 8, 0);
+    debug_assert_eq!(src.align_offset(8), 0);
+    debug_assert_eq!(dst.align_offset(8), 0);
+    let mask = mask_u64(cnd);
+    let src = src as *const u64;
+    let dst = dst as *mut u64;
+    for i in 0..count / 8 {
+        let a = dst.add(i).read();
+        let b = src.add(i).read();
+        dst.add(i).write(a ^ (mask & (a ^ b)));
+    }
+}
@@ -0,0 +1,53 @@
+use super::{cmov_bytes_a1, cmov_bytes_a16, cmov_bytes_a32, cmov_bytes_a64, cmov_bytes_a8};
+use super::{CMov, CtOrd};
+use crate::aligned::{AlignedBox, Alignment};
+
+/// Read `slice[idx]` without revealing `idx`, by scanning the whole slice.
+///
+/// Panic if `idx` is out of bounds.
+pub fn ct_read<T: CMov>(slice: &[T], idx: usize) -> T {
+    assert!(idx < slice.len(), "index out of bounds");
+    let mut ret = slice[0].clone();
+    for (i, item) in slice.iter().enumerate() {
+        ret.cnd_assign(item, i.ct_eq(&idx));
+    }
+    ret
+}
+
+/// Write `value` to `slice[idx]` without revealing `idx`, by scanning the whole slice.
+///
+/// Panic if `idx` is out of bounds.
+pub fn ct_write<T: CMov>(slice: &mut [T], idx: usize, value: &T) {
+    assert!(idx < slice.len(), "index out of bounds");
+    for (i, item) in slice.iter_mut().enumerate() {
+        item.cnd_assign(value, i.ct_eq(&idx));
+    }
+}
+
+/// Copy all elements from `src` into `dst` if choice is true, with the `cmov_bytes_a*` matching
+/// the alignment `A`.
+///
+/// Panic if the two slices have different lengths.
+pub fn cnd_copy_from_slice<A: Alignment, T: Copy>(
+    dst: &mut AlignedBox<A, [T]>,
+    src: &AlignedBox<A, [T]>,
+    choice: bool,
+) {
+    assert_eq!(dst.len(), src.len(), "length mismatch");
+    let count = dst.cmov_byte_size();
+    if count == 0 {
+        return;
+    }
+    let src = src.as_ptr() as *const u8;
+    let dst = dst.as_mut_ptr() as *mut u8;
+    // SAFETY: both buffers are allocated with `count` bytes aligned to `A::SIZE`.
+    unsafe {
+        match A::SIZE {
+            8 => cmov_bytes_a8(choice, src, dst, count),
+            16 => cmov_bytes_a16(choice, src, dst, count),
+            32 => cmov_bytes_a32(choice, src, dst, count),
+            64 => cmov_bytes_a64(choice, src, dst, count),
+            _ => cmov_bytes_a1(choice, src, dst, count),
+        }
+    }
+}
@@ -37,6 +37,8 @@ test_cmov!(test_u64, u64);
 test_cmov!(test_i64, i64);
 test_cmov!(test_usize, usize);
 test_cmov!(test_isize, isize);
+test_cmov!(test_f32, f32);
+test_cmov!(test_f64, f64);
 test_cmov!(test_a8, Aligned<A8, [u64; 16]>);
 test_cmov!(test_a16, Aligned<A16, [u64; 16]>);
 test_cmov!(test_a32, Aligned<A32, [u64; 16]>);
@@ -83,3 +85,276 @@ fn test_aligned_slice_box() {
     assert_eq!(dst, a);
     assert_eq!(<_>::cnd_select(&a, &b, choice), b);
 }
+
+#[test]
+fn test_f64_bits() {
+    let a = f64::from_bits(0x7ff8_dead_beef_0001);
+    let b = -0.0f64;
+    assert_eq!(f64::cnd_select(&a, &b, false).to_bits(), a.to_bits());
+    assert_eq!(f64::cnd_select(&a, &b, true).to_bits(), b.to_bits());
+}
+
+proptest! {
+    #[test]
+    fn test_vec(choice in prop::bool::ANY, ab in prop::collection::vec(any::<(u64, u64)>(), 0..50)) {
+        let (a, b): (Vec<_>, Vec<_>) = ab.into_iter().unzip();
+        let mut dst = b.clone();
+        dst.cnd_assign(&a, choice);
+        prop_assert_eq!(&dst, if choice { &a } else { &b });
+
+        let mut x = a.clone();
+        let mut y = b.clone();
+        <_ as CMov>::cnd_swap(&mut x, &mut y, choice);
+        prop_assert_eq!(&x, if choice { &b } else { &a });
+        prop_assert_eq!(&y, if choice { &a } else { &b });
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_vec_len_mismatch() {
+    let mut a = vec![0u64; 2];
+    a.cnd_assign(&vec![0u64; 3], false);
+}
+
+macro_rules! test_ct_ord {
+    ($name: ident, $ty: ty) => {
+        proptest! {
+            #[test]
+            fn $name(a in any::<$ty>(), b in any::<$ty>()) {
+                prop_assert_eq!(a.ct_lt(&b), a < b);
+                prop_assert_eq!(a.ct_eq(&b), a == b);
+                prop_assert_eq!(a.ct_gt(&b), a > b);
+                prop_assert_eq!(a.ct_le(&b), a <= b);
+                prop_assert_eq!(a.ct_ge(&b), a >= b);
+                prop_assert!(a.ct_eq(&a));
+                prop_assert!(!a.ct_lt(&a));
+            }
+        }
+    };
+}
+
+test_ct_ord!(test_ct_ord_bool, bool);
+test_ct_ord!(test_ct_ord_u8, u8);
+test_ct_ord!(test_ct_ord_i8, i8);
+test_ct_ord!(test_ct_ord_u16, u16);
+test_ct_ord!(test_ct_ord_i16, i16);
+test_ct_ord!(test_ct_ord_u32, u32);
+test_ct_ord!(test_ct_ord_i32, i32);
+test_ct_ord!(test_ct_ord_u64, u64);
+test_ct_ord!(test_ct_ord_i64, i64);
+test_ct_ord!(test_ct_ord_u128, u128);
+test_ct_ord!(test_ct_ord_i128, i128);
+test_ct_ord!(test_ct_ord_usize, usize);
+test_ct_ord!(test_ct_ord_isize, isize);
+test_ct_ord!(test_ct_ord_tuple, (u8, bool, i16));
+test_ct_ord!(test_ct_ord_array, [u8; 3]);
+test_ct_ord!(test_ct_ord_a8, Aligned<A8, [u8; 8]>);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, CtOrd)]
+struct TestCtOrdStruct {
+    a: u8,
+    b: i32,
+    c: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, CtOrd)]
+struct TestCtOrdTupleStruct(u8, u8);
+
+proptest! {
+    #[test]
+    fn test_derive_ct_ord(a in (0u8..2, -1i32..1, any::<bool>()), b in (0u8..2, -1i32..1, any::<bool>()), c in any::<(u8, u8)>(), d in any::<(u8, u8)>()) {
+        let (a, b) = (
+            TestCtOrdStruct { a: a.0, b: a.1, c: a.2 },
+            TestCtOrdStruct { a: b.0, b: b.1, c: b.2 },
+        );
+        prop_assert_eq!(a.ct_lt(&b), a < b);
+        prop_assert_eq!(a.ct_eq(&b), a == b);
+
+        let (c, d) = (TestCtOrdTupleStruct(c.0, c.1), TestCtOrdTupleStruct(d.0, d.1));
+        prop_assert_eq!(c.ct_lt(&d), c < d);
+        prop_assert_eq!(c.ct_eq(&d), c == d);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, CMov)]
+enum TestEnum {
+    A,
+    B(u8, u64),
+    C { x: u16 },
+}
+
+fn arb_test_enum() -> impl Strategy<Value = TestEnum> {
+    prop_oneof![
+        Just(TestEnum::A),
+        any::<(u8, u64)>().prop_map(|(a, b)| TestEnum::B(a, b)),
+        any::<u16>().prop_map(|x| TestEnum::C { x }),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq, CMov)]
+struct TestSkipStruct {
+    value: u64,
+    #[cmov(skip)]
+    label: &'static str,
+}
+
+#[derive(Clone, CMov)]
+#[cmov(bound = "T: Clone")]
+struct TestBoundStruct<T> {
+    value: u64,
+    #[cmov(skip)]
+    marker: core::marker::PhantomData<T>,
+}
+
+proptest! {
+    #[test]
+    fn test_derive_enum(choice in prop::bool::ANY, a in arb_test_enum(), b in arb_test_enum()) {
+        let mut dst = b;
+        dst.cnd_assign(&a, choice);
+        prop_assert_eq!(dst, if choice { a } else { b });
+        prop_assert_eq!(TestEnum::cnd_select(&a, &b, choice), if choice { b } else { a });
+
+        let (mut x, mut y) = (a, b);
+        TestEnum::cnd_swap(&mut x, &mut y, choice);
+        prop_assert_eq!((x, y), if choice { (b, a) } else { (a, b) });
+    }
+
+    #[test]
+    fn test_derive_skip(choice in prop::bool::ANY, a in any::<u64>(), b in any::<u64>()) {
+        let x = TestSkipStruct { value: a, label: "x" };
+        let y = TestSkipStruct { value: b, label: "y" };
+        let mut dst = x;
+        dst.cnd_assign(&y, choice);
+        prop_assert_eq!(dst.value, if choice { b } else { a });
+        prop_assert_eq!(dst.label, "x");
+
+        // A type parameter which is not `CMov`.
+        #[derive(Clone)]
+        struct NotCMov;
+        let x = TestBoundStruct::<NotCMov> { value: a, marker: Default::default() };
+        let y = TestBoundStruct::<NotCMov> { value: b, marker: Default::default() };
+        prop_assert_eq!(<_ as CMov>::cnd_select(&x, &y, choice).value, if choice { b } else { a });
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_cmov_bytes_a1(choice in prop::bool::ANY, a in any::<[u8; 13]>(), b in any::<[u8; 13]>()) {
+        let mut dst = b;
+        unsafe { cmov_bytes_a1(choice, a.as_ptr(), dst.as_mut_ptr(), a.len()) };
+        prop_assert_eq!(dst, if choice { a } else { b });
+    }
+}
+
+/// Compare the asm backend against the portable one.
+#[cfg(all(target_arch = "x86_64", not(feature = "portable-cmov")))]
+mod differential {
+    use super::*;
+    use crate::cmov::{impl_u32_u64_usize, portable};
+
+    proptest! {
+        #[test]
+        fn test_u32(choice in prop::bool::ANY, a in any::<u32>(), b in any::<u32>()) {
+            prop_assert_eq!(
+                impl_u32_u64_usize::cmov_u32(choice, a, b),
+                portable::cmov_u32(choice, a, b)
+            );
+        }
+
+        #[test]
+        fn test_u64(choice in prop::bool::ANY, a in any::<u64>(), b in any::<u64>()) {
+            prop_assert_eq!(
+                impl_u32_u64_usize::cmov_u64(choice, a, b),
+                portable::cmov_u64(choice, a, b)
+            );
+        }
+
+        #[test]
+        fn test_bytes_a1(choice in prop::bool::ANY, a in any::<[u8; 13]>(), b in any::<[u8; 13]>()) {
+            let mut asm_dst = b;
+            let mut portable_dst = b;
+            unsafe {
+                cmov_bytes_a1(choice, a.as_ptr(), asm_dst.as_mut_ptr(), a.len());
+                portable::cmov_bytes_a1(choice, a.as_ptr(), portable_dst.as_mut_ptr(), a.len());
+            }
+            prop_assert_eq!(asm_dst, portable_dst);
+        }
+
+        #[test]
+        fn test_bytes_a8(
+            choice in prop::bool::ANY,
+            a in any::<[u64; 16]>(),
+            b in any::<[u64; 16]>(),
+        ) {
+            let a = Aligned::<A64, _>::new(a);
+            let src = &a as *const _ as *const u8;
+            let size = core::mem::size_of_val(&a);
+            let mut expected = Aligned::<A64, _>::new(b);
+            unsafe { portable::cmov_bytes_a8(choice, src, &mut expected as *mut _ as *mut u8, size) };
+            for func in [cmov_bytes_a8, cmov_bytes_a16, cmov_bytes_a32, cmov_bytes_a64] {
+                let mut dst = Aligned::<A64, _>::new(b);
+                unsafe { func(choice, src, &mut dst as *mut _ as *mut u8, size) };
+                prop_assert_eq!(dst, expected);
+            }
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_ct_read_write(
+        (idx, a) in prop::collection::vec(any::<u64>(), 1..50)
+            .prop_flat_map(|a| (0..a.len(), Just(a))),
+        value in any::<u64>(),
+    ) {
+        prop_assert_eq!(ct_read(&a, idx), a[idx]);
+
+        let mut res = a.clone();
+        ct_write(&mut res, idx, &value);
+        let mut expected = a;
+        expected[idx] = value;
+        prop_assert_eq!(res, expected);
+    }
+}
+
+#[test]
+#[should_panic(expected = "index out of bounds")]
+fn test_ct_read_out_of_bounds() {
+    ct_read(&[1u64, 2, 3], 3);
+}
+
+macro_rules! test_cnd_copy_from_slice {
+    ($name: ident, $align: ty) => {
+        proptest! {
+            #[test]
+            fn $name(
+                choice in prop::bool::ANY,
+                (a, b) in (0..50usize).prop_flat_map(|len| (
+                    prop::collection::vec(any::<u16>(), len),
+                    prop::collection::vec(any::<u16>(), len),
+                )),
+            ) {
+                let src: AlignedBox<$align, [u16]> = a.clone().into();
+                let mut dst: AlignedBox<$align, [u16]> = b.clone().into();
+                cnd_copy_from_slice(&mut dst, &src, choice);
+                prop_assert_eq!(&*src, &a[..]);
+                prop_assert_eq!(&*dst, if choice { &a[..] } else { &b[..] });
+            }
+        }
+    };
+}
+
+test_cnd_copy_from_slice!(test_cnd_copy_from_slice_a2, crate::aligned::A2);
+test_cnd_copy_from_slice!(test_cnd_copy_from_slice_a8, A8);
+test_cnd_copy_from_slice!(test_cnd_copy_from_slice_a16, A16);
+test_cnd_copy_from_slice!(test_cnd_copy_from_slice_a32, A32);
+test_cnd_copy_from_slice!(test_cnd_copy_from_slice_a64, A64);
+
+#[test]
+#[should_panic(expected = "length mismatch")]
+fn test_cnd_copy_from_slice_len_mismatch() {
+    let src: AlignedBox<A8, [u64]> = vec![1u64, 2].into();
+    let mut dst: AlignedBox<A8, [u64]> = vec![1u64].into();
+    cnd_copy_from_slice(&mut dst, &src, true);
+}
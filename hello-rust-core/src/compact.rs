@@ -0,0 +1,134 @@
+//! Order-preserving oblivious compaction.
+//! Ref: Sasy, Johnson and Goldberg. Fast Fully Oblivious Compaction and Shuffling. CCS 2022.
+
+use crate::cmov::{CMov, CndOption};
+use alloc::vec::Vec;
+
+/// Prefix sums of marks, i.e., `prefix[i]` is the number of marked items in `marks[..i]`.
+fn mark_prefix_sums(marks: impl Iterator<Item = bool>, len: usize) -> Vec<usize> {
+    let mut prefix = Vec::with_capacity(len + 1);
+    let mut count = 0;
+    prefix.push(count);
+    for mark in marks {
+        count += mark as usize;
+        prefix.push(count);
+    }
+    assert_eq!(prefix.len(), len + 1, "marks should have the same length");
+    prefix
+}
+
+/// Compact an array whose length is a power of two, such that marked items are placed
+/// cyclically starting from `offset`.
+fn compact_offset<T: CMov>(array: &mut [T], prefix: &[usize], offset: usize) {
+    let len = array.len();
+    debug_assert!(len.is_power_of_two());
+    if len < 2 {
+        return;
+    }
+
+    let half = len / 2;
+    // `half` is a power of two, so a mask replaces `%`, whose latency depends on the operands.
+    debug_assert!(half.is_power_of_two());
+    let mask = half - 1;
+    let count = prefix[half] - prefix[0];
+    let (left, right) = array.split_at_mut(half);
+    if len == 2 {
+        let m1 = prefix[2] - prefix[1] != 0;
+        <_ as CMov>::cnd_swap(
+            &mut left[0],
+            &mut right[0],
+            ((count == 0) & m1) ^ (offset != 0),
+        );
+        return;
+    }
+
+    compact_offset(left, &prefix[..=half], offset & mask);
+    compact_offset(right, &prefix[half..], (offset + count) & mask);
+    let wrap = ((offset & mask) + count >= half) ^ (offset >= half);
+    let pivot = (offset + count) & mask;
+    for (i, (a, b)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+        <_ as CMov>::cnd_swap(a, b, wrap ^ (i >= pivot));
+    }
+}
+
+fn compact_inner<T: CMov>(array: &mut [T], prefix: &[usize]) {
+    let len = array.len();
+    if len < 2 {
+        return;
+    }
+    let n1 = 1 << len.ilog2();
+    let n2 = len - n1;
+    let count = prefix[n2] - prefix[0];
+    let (left, right) = array.split_at_mut(n2);
+    compact_inner(left, &prefix[..=n2]);
+    compact_offset(right, &prefix[n2..], (n1 - n2 + count) & (n1 - 1));
+    let (left, right) = array.split_at_mut(n1);
+    for (i, (a, b)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+        <_ as CMov>::cnd_swap(a, b, i >= count);
+    }
+}
+
+/// Move marked items to the front of `array` while preserving their relative order.
+/// The order of the remaining items is unspecified.
+///
+/// Return the number of marked items. It costs O(n log n) `cnd_swap` with a fixed access pattern.
+///
+/// Panic if `marks` and `array` have different lengths.
+pub fn compact<T: CMov>(array: &mut [T], marks: &[bool]) -> usize {
+    let prefix = mark_prefix_sums(marks.iter().copied(), array.len());
+    compact_inner(array, &prefix);
+    prefix[array.len()]
+}
+
+/// Move items satisfying `f` to the front of `array` while preserving their relative order.
+#[inline]
+pub fn compact_by<T, F>(array: &mut [T], f: F) -> usize
+where
+    T: CMov,
+    F: FnMut(&T) -> bool,
+{
+    let prefix = mark_prefix_sums(array.iter().map(f), array.len());
+    compact_inner(array, &prefix);
+    prefix[array.len()]
+}
+
+/// Move `Some` items to the front of `array` while preserving their relative order.
+#[inline]
+pub fn compact_options<T: CMov>(array: &mut [CndOption<T>]) -> usize {
+    compact_by(array, CndOption::is_some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_compact(input in prop::collection::vec((any::<u32>(), any::<bool>()), 0..1000)) {
+            let mut expected = input.clone();
+            expected.retain(|x| x.1);
+
+            let mut output = input.clone();
+            let marks: Vec<bool> = input.iter().map(|x| x.1).collect();
+            let count = compact(&mut output, &marks);
+            prop_assert_eq!(count, expected.len());
+            prop_assert_eq!(&output[..count], &expected[..]);
+
+            let mut output = input;
+            let count = compact_by(&mut output, |x| x.1);
+            prop_assert_eq!(&output[..count], &expected[..]);
+        }
+
+        #[test]
+        fn test_compact_options(input in prop::collection::vec(any::<Option<u64>>(), 0..1000)) {
+            let mut expected = input.clone();
+            expected.retain(Option::is_some);
+
+            let mut output: Vec<CndOption<u64>> = input.into_iter().map(Into::into).collect();
+            let count = compact_options(&mut output);
+            let output: Vec<Option<u64>> = output.into_iter().take(count).map(Into::into).collect();
+            prop_assert_eq!(output, expected);
+        }
+    }
+}
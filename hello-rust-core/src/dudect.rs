@@ -0,0 +1,141 @@
+//! Constant-time testing in the style of dudect.
+//! Ref: Reparaz, Balasch and Verbauwhede. Dude, is my code constant time? DATE 2017.
+//!
+//! A function is run on inputs of two classes chosen at random, and the cycle counts of both
+//! classes are compared by Welch's t-test. Measurements above several percentiles are also tested
+//! separately, since timing noise is mostly in the upper tail.
+//!
+//! The module is only built for tests, since `rdtsc` faults inside SGX1 enclaves.
+
+use alloc::vec::Vec;
+use core::arch::x86_64::{_mm_lfence, _rdtsc};
+use rand::{Rng, RngCore};
+
+/// Threshold of `|t|` above which the timing definitely depends on the input class.
+pub const T_THRESHOLD: f64 = 10.0;
+
+/// Number of percentiles to crop measurements at.
+const NUM_PERCENTILES: usize = 20;
+
+/// Welch's t-test over two classes, updated online.
+#[derive(Debug, Clone, Default)]
+pub struct WelchTTest {
+    count: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl WelchTTest {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add sample `x` to `class`.
+    pub fn push(&mut self, class: bool, x: f64) {
+        let c = class as usize;
+        self.count[c] += 1.0;
+        let delta = x - self.mean[c];
+        self.mean[c] += delta / self.count[c];
+        self.m2[c] += delta * (x - self.mean[c]);
+    }
+
+    /// Number of samples of both classes.
+    #[inline]
+    pub fn len(&self) -> usize {
+        (self.count[0] + self.count[1]) as usize
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The t statistic. It is zero if any class has less than two samples.
+    pub fn t(&self) -> f64 {
+        if self.count[0] < 2.0 || self.count[1] < 2.0 {
+            return 0.0;
+        }
+        let var0 = self.m2[0] / (self.count[0] - 1.0);
+        let var1 = self.m2[1] / (self.count[1] - 1.0);
+        let se = libm::sqrt(var0 / self.count[0] + var1 / self.count[1]);
+        if se == 0.0 {
+            return 0.0;
+        }
+        (self.mean[0] - self.mean[1]) / se
+    }
+}
+
+/// Result of `measure`.
+#[derive(Debug, Clone)]
+pub struct Report {
+    /// Number of measurements.
+    pub samples: usize,
+    /// Maximum `|t|` over all and cropped measurements.
+    pub max_t: f64,
+}
+
+impl Report {
+    /// Whether the timing leaks the input class, i.e. `max_t > T_THRESHOLD`.
+    #[inline]
+    pub fn leaks(&self) -> bool {
+        self.max_t > T_THRESHOLD
+    }
+}
+
+#[inline(always)]
+fn cycles() -> u64 {
+    unsafe {
+        _mm_lfence();
+        let t = _rdtsc();
+        _mm_lfence();
+        t
+    }
+}
+
+/// Measure `f` for `samples` times on inputs generated by `gen_input` for a random class.
+///
+/// Input generation is not timed.
+pub fn measure<T, R, G, F>(samples: usize, rng: &mut R, mut gen_input: G, mut f: F) -> Report
+where
+    R: RngCore + ?Sized,
+    G: FnMut(bool, &mut R) -> T,
+    F: FnMut(&mut T),
+{
+    let mut measurements = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let class = rng.gen::<bool>();
+        let mut input = gen_input(class, rng);
+        let start = cycles();
+        f(&mut input);
+        let end = cycles();
+        measurements.push((class, end.wrapping_sub(start) as f64));
+    }
+
+    let mut sorted: Vec<f64> = measurements.iter().map(|(_, x)| *x).collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mut thresholds = vec![f64::INFINITY];
+    if !sorted.is_empty() {
+        for i in 0..NUM_PERCENTILES {
+            let p = 1.0 - libm::pow(0.5, 10.0 * (i + 1) as f64 / NUM_PERCENTILES as f64);
+            thresholds.push(sorted[(p * sorted.len() as f64) as usize]);
+        }
+    }
+
+    let mut tests = vec![WelchTTest::new(); thresholds.len()];
+    for (class, x) in measurements {
+        for (test, threshold) in tests.iter_mut().zip(&thresholds) {
+            if x < *threshold {
+                test.push(class, x);
+            }
+        }
+    }
+
+    let max_t = tests
+        .iter()
+        .map(|test| libm::fabs(test.t()))
+        .fold(0.0, f64::max);
+    Report { samples, max_t }
+}
+
+mod tests;
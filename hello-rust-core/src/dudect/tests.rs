@@ -0,0 +1,159 @@
+//! Per-primitive checks are ignored by default, since timing is only meaningful in release
+//! builds on an idle machine. Run them with
+//! `cargo test --release -p hello-rust-core dudect -- --ignored --test-threads=1`.
+
+use super::*;
+use crate::{
+    aligned::{Aligned, A16, A32, A64, A8},
+    cmov::{cmov_bytes_a16, cmov_bytes_a32, cmov_bytes_a64, cmov_bytes_a8, CMov, CtOrd},
+    sort::{bitonic_sort_by, bitonic_sort_ct, oblivious_shuffle},
+};
+use core::hint::black_box;
+use rand::{
+    rngs::{mock::StepRng, StdRng},
+    SeedableRng,
+};
+
+const SAMPLES: usize = 100_000;
+
+fn assert_constant_time(name: &str, report: Report) {
+    println!(
+        "{name}: max |t| = {:.2} over {} samples",
+        report.max_t, report.samples
+    );
+    assert!(!report.leaks(), "{name} leaks timing: {report:?}");
+}
+
+#[test]
+fn test_welch_t_test() {
+    let mut test = WelchTTest::new();
+    assert_eq!(test.t(), 0.0);
+    for x in [1.0, 2.0, 3.0, 4.0] {
+        test.push(false, x);
+    }
+    for x in [3.0, 4.0, 5.0, 6.0] {
+        test.push(true, x);
+    }
+    assert_eq!(test.len(), 8);
+    // Both variances are 5/3, so t = -2 / sqrt(5/3 / 4 * 2).
+    let expected = -2.0 / libm::sqrt(5.0 / 6.0);
+    assert!((test.t() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn test_detect_leak() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let report = measure(
+        2_000,
+        &mut rng,
+        |class, _| if class { 2_000u64 } else { 0 },
+        |n| {
+            for i in 0..*n {
+                black_box(i);
+            }
+        },
+    );
+    assert!(report.leaks(), "{report:?}");
+}
+
+#[test]
+#[ignore]
+fn test_cmov_u64() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let report = measure(
+        SAMPLES,
+        &mut rng,
+        |class, rng| (class, [0u64; 64].map(|_| rng.next_u64())),
+        |(choice, values)| {
+            let mut acc = 0u64;
+            for v in values.iter() {
+                acc = u64::cnd_select(&acc, v, black_box(*choice));
+            }
+            black_box(acc);
+        },
+    );
+    assert_constant_time("cmov_u64", report);
+}
+
+macro_rules! test_cmov_bytes {
+    ($name: ident, $f: ident, $align: ty) => {
+        #[test]
+        #[ignore]
+        fn $name() {
+            let mut rng = StdRng::seed_from_u64(0);
+            let src = Aligned::<$align, [u8; 4096]>::new([0xa5; 4096]);
+            let mut dst = Aligned::<$align, [u8; 4096]>::new([0; 4096]);
+            let report = measure(
+                SAMPLES,
+                &mut rng,
+                |class, _| class,
+                |choice| unsafe { $f(black_box(*choice), src.as_ptr(), dst.as_mut_ptr(), 4096) },
+            );
+            assert_constant_time(stringify!($f), report);
+        }
+    };
+}
+
+test_cmov_bytes!(test_cmov_bytes_a8, cmov_bytes_a8, A8);
+test_cmov_bytes!(test_cmov_bytes_a16, cmov_bytes_a16, A16);
+test_cmov_bytes!(test_cmov_bytes_a32, cmov_bytes_a32, A32);
+test_cmov_bytes!(test_cmov_bytes_a64, cmov_bytes_a64, A64);
+
+/// Sorted input for class `false`, and reversed for `true`.
+fn sorted_or_reversed(class: bool) -> Vec<u64> {
+    let sorted: Vec<u64> = (0..256).collect();
+    if class {
+        sorted.into_iter().rev().collect()
+    } else {
+        sorted
+    }
+}
+
+/// The comparator is part of the measured code, so it is built from `ct_lt` instead of
+/// `Ord::cmp`, which may branch on data.
+#[test]
+#[ignore]
+fn test_bitonic_sort_by() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let report = measure(
+        SAMPLES / 10,
+        &mut rng,
+        |class, _| sorted_or_reversed(class),
+        |array| bitonic_sort_by(array, |a, b| (!a.ct_lt(b) as u8).cmp(&1)),
+    );
+    assert_constant_time("bitonic_sort_by", report);
+}
+
+#[test]
+#[ignore]
+fn test_bitonic_sort_ct() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let report = measure(
+        SAMPLES / 10,
+        &mut rng,
+        |class, _| sorted_or_reversed(class),
+        |array| bitonic_sort_ct(array),
+    );
+    assert_constant_time("bitonic_sort_ct", report);
+}
+
+#[test]
+#[ignore]
+fn test_oblivious_shuffle() {
+    let mut rng = StdRng::seed_from_u64(0);
+    let report = measure(
+        SAMPLES / 10,
+        &mut rng,
+        |class, _| {
+            // Ascending or descending tags, which lead to opposite swap decisions.
+            let tags = if class {
+                StepRng::new(u64::MAX, u64::MAX)
+            } else {
+                StepRng::new(0, 1)
+            };
+            (vec![0u64; 256], tags)
+        },
+        |(array, tags)| oblivious_shuffle(array, tags),
+    );
+    assert_constant_time("oblivious_shuffle", report);
+}
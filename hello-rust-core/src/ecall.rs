@@ -0,0 +1,224 @@
+use crate::util::DimensionMismatch;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub use hello_rust_ecall_macro::enclave_api;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::vec::Vec;
+}
+
+/// Error of a typed ecall, on either side of the enclave boundary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EcallError {
+    /// Failed to encode a request or a response.
+    Serialize,
+    /// Failed to decode a request or a response.
+    Deserialize,
+    /// The enclave does not know the method of this index.
+    UnknownMethod(u32),
+    /// The ecall failed with this `sgx_status_t`.
+    Sgx(u32),
+    /// The ecall returned this non-zero code.
+    Enclave(i32),
+    /// The enclave did not return a response.
+    NoResponse,
+    /// Error raised by the implementation of the API.
+    Message(String),
+    /// The implementation of the API panicked.
+    Panic,
+}
+
+impl fmt::Display for EcallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serialize => write!(f, "failed to encode ecall data"),
+            Self::Deserialize => write!(f, "failed to decode ecall data"),
+            Self::UnknownMethod(method) => write!(f, "unknown ecall method {method}"),
+            Self::Sgx(status) => write!(f, "ecall failed with sgx status {status:#x}"),
+            Self::Enclave(code) => write!(f, "ecall returned {code}"),
+            Self::NoResponse => write!(f, "ecall returned no response"),
+            Self::Message(msg) => write!(f, "{msg}"),
+            Self::Panic => write!(f, "ecall panicked"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EcallError {}
+
+impl From<DimensionMismatch> for EcallError {
+    #[inline]
+    fn from(err: DimensionMismatch) -> Self {
+        Self::Message(err.to_string())
+    }
+}
+
+pub type Result<T> = core::result::Result<T, EcallError>;
+
+/// Channel from the host stub to the enclave dispatcher of an API declared by `enclave_api`.
+pub trait EcallTransport {
+    /// Send an encoded request to the enclave and return the encoded response.
+    fn ecall(&self, request: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Encode `value` by postcard.
+#[inline]
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    postcard::to_allocvec(value).map_err(|_| EcallError::Serialize)
+}
+
+/// Decode a value encoded by postcard.
+#[inline]
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    postcard::from_bytes(bytes).map_err(|_| EcallError::Deserialize)
+}
+
+/// Decode the method index of `request`, and run `call` on it and the encoded arguments.
+///
+/// `call` returns the encoded `Ok` response. If it fails, the error is encoded as the response.
+///
+/// With the `std` feature, a panic in `call` is returned as `EcallError::Panic`. Without it, the
+/// enclave must be built with `panic = "abort"`, so that no panic unwinds across the ecall.
+pub fn dispatch<F>(request: &[u8], call: F) -> Vec<u8>
+where
+    F: FnOnce(u32, &[u8]) -> Result<Vec<u8>>,
+{
+    let response = postcard::take_from_bytes::<u32>(request)
+        .map_err(|_| EcallError::Deserialize)
+        .and_then(|(method, args)| catch_panic(|| call(method, args)));
+    // An empty response is reported as `Deserialize` by the host.
+    response.unwrap_or_else(|err| encode(&Result::<()>::Err(err)).unwrap_or_default())
+}
+
+#[cfg(feature = "std")]
+fn catch_panic<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or(Err(EcallError::Panic))
+}
+
+#[cfg(not(feature = "std"))]
+#[inline]
+fn catch_panic<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::*;
+    use crate::util::{compute_l2_distance, L2Dist, Point};
+
+    #[enclave_api]
+    trait TestApi {
+        fn nothing(&self) -> Result<()>;
+        fn add(&self, a: u64, b: u64) -> Result<u64>;
+        fn fail(&self, msg: String) -> Result<u8>;
+        fn panic(&self) -> Result<()>;
+    }
+
+    struct TestImpl;
+
+    impl TestApi for TestImpl {
+        fn nothing(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn add(&self, a: u64, b: u64) -> Result<u64> {
+            Ok(a + b)
+        }
+
+        fn fail(&self, msg: String) -> Result<u8> {
+            Err(EcallError::Message(msg))
+        }
+
+        fn panic(&self) -> Result<()> {
+            panic!("oops")
+        }
+    }
+
+    impl EnclaveApi for TestImpl {
+        fn l2_dist(&self, pairs: Vec<(Point, Point)>) -> Result<Vec<L2Dist>> {
+            pairs
+                .iter()
+                .map(|(a, b)| Ok(compute_l2_distance(a, b)?))
+                .collect()
+        }
+    }
+
+    /// Transport which dispatches requests in the same process.
+    struct Loopback<F>(F);
+
+    impl<F: Fn(&[u8]) -> Vec<u8>> EcallTransport for Loopback<F> {
+        fn ecall(&self, request: &[u8]) -> Result<Vec<u8>> {
+            Ok((self.0)(request))
+        }
+    }
+
+    #[test]
+    fn test_client() {
+        let client = TestApiClient(Loopback(|req: &[u8]| dispatch_test_api(&TestImpl, req)));
+        assert_eq!(client.nothing(), Ok(()));
+        assert_eq!(client.add(1, 2), Ok(3));
+        assert_eq!(
+            client.fail("oops".into()),
+            Err(EcallError::Message("oops".into()))
+        );
+        assert_eq!(client.panic(), Err(EcallError::Panic));
+    }
+
+    #[test]
+    fn test_enclave_api() {
+        let client = EnclaveApiClient(Loopback(|req: &[u8]| dispatch_enclave_api(&TestImpl, req)));
+        let point = |point_vec: Vec<f64>| Point { point_vec };
+        let pairs = vec![
+            (point(vec![1.0, 0.0]), point(vec![0.0, 1.0])),
+            (point(vec![0.0, 0.0]), point(vec![3.0, 4.0])),
+        ];
+        let dists: Vec<f64> = client
+            .l2_dist(pairs)
+            .unwrap()
+            .iter()
+            .map(|l2| l2.dist)
+            .collect();
+        assert_eq!(dists, vec![2f64.sqrt(), 5.0]);
+
+        let pairs = vec![(point(vec![1.0, 0.0]), point(vec![0.0, 1.0, 2.0]))];
+        assert_eq!(
+            client.l2_dist(pairs).unwrap_err(),
+            EcallError::Message("dimensions 2 and 3 differ".into())
+        );
+    }
+
+    #[test]
+    fn test_dispatch_error() {
+        let response = dispatch_test_api(&TestImpl, &encode(&(7u32, ())).unwrap());
+        assert_eq!(
+            decode::<Result<u64>>(&response).unwrap(),
+            Err(EcallError::UnknownMethod(7))
+        );
+
+        // Method `add` with a missing argument.
+        let response = dispatch_test_api(&TestImpl, &encode(&(1u32, (1u64,))).unwrap());
+        assert_eq!(
+            decode::<Result<u64>>(&response).unwrap(),
+            Err(EcallError::Deserialize)
+        );
+
+        let response = dispatch_test_api(&TestImpl, &[]);
+        assert_eq!(
+            decode::<Result<u64>>(&response).unwrap(),
+            Err(EcallError::Deserialize)
+        );
+    }
+
+    #[test]
+    fn test_transport_error() {
+        let client = TestApiClient(Loopback(|_: &[u8]| Vec::new()));
+        assert_eq!(client.add(1, 2), Err(EcallError::Deserialize));
+    }
+}
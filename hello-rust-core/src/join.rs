@@ -0,0 +1,322 @@
+//! Oblivious equi-join.
+//! Ref: Krastnikov, Kerschbaum and Stebila. Efficient Oblivious Database Joins. VLDB 2020.
+
+use crate::{
+    cmov::{CMov, CndOption},
+    compact::compact_by,
+    sort::bitonic_sort_by,
+};
+use alloc::vec::Vec;
+
+/// A row of the union of both tables.
+#[derive(Clone, Default, CMov)]
+struct UnionRow<K: CMov, L: CMov, R: CMov> {
+    key: K,
+    is_right: bool,
+    /// Index of the row in its table.
+    index: usize,
+    left: L,
+    right: R,
+    /// Number of right rows with the same key.
+    right_count: usize,
+    /// Number of right rows with smaller keys.
+    right_start: usize,
+}
+
+/// An entry of the oblivious gather of right rows into output slots.
+#[derive(Clone, Default, CMov)]
+struct GatherEntry<R: CMov> {
+    /// Index of the right row, or `usize::MAX` for an empty slot.
+    index: usize,
+    is_request: bool,
+    /// Output slot of a request.
+    slot: usize,
+    value: R,
+}
+
+/// A slot of the expanded table.
+#[derive(Clone, Default, CMov)]
+struct ExpandSlot<T: CMov> {
+    dest: usize,
+    end: usize,
+    is_dummy: bool,
+    value: CndOption<T>,
+}
+
+/// Duplicate `rows[i]` for `count(&rows[i])` times into an array of `output_len` slots.
+/// Unused slots are `None`, and rows beyond `output_len` are dropped.
+fn expand<T, F>(rows: &[T], mut count: F, output_len: usize) -> Vec<CndOption<T>>
+where
+    T: CMov + Default,
+    F: FnMut(&T) -> usize,
+{
+    let mut slots = Vec::with_capacity(rows.len() + output_len);
+    let mut dest = 0;
+    for row in rows {
+        let c = count(row);
+        slots.push(ExpandSlot {
+            dest: usize::cnd_select(&dest, &usize::MAX, c == 0),
+            end: dest + c,
+            is_dummy: false,
+            value: CndOption::new_some(row.clone()),
+        });
+        dest += c;
+    }
+    slots.extend((0..output_len).map(|j| ExpandSlot {
+        dest: j,
+        end: j,
+        is_dummy: true,
+        value: CndOption::new_none(),
+    }));
+
+    // Every row is followed by the dummy slots it should be copied to.
+    bitonic_sort_by(&mut slots, |a, b| {
+        a.dest.cmp(&b.dest).then(a.is_dummy.cmp(&b.is_dummy))
+    });
+
+    let mut carry = CndOption::new_none();
+    let mut carry_end = 0;
+    for slot in slots.iter_mut() {
+        carry.cnd_assign(&slot.value, !slot.is_dummy);
+        carry_end.cnd_assign(&slot.end, !slot.is_dummy);
+        let fill = slot.is_dummy & (slot.dest < carry_end);
+        slot.value.cnd_assign(&carry, fill);
+    }
+
+    compact_by(&mut slots, |slot| slot.is_dummy);
+    slots.truncate(output_len);
+    slots.into_iter().map(|slot| slot.value).collect()
+}
+
+/// Result of `equi_join`, padded to a public length.
+#[derive(Debug, Clone)]
+pub struct JoinResult<L: CMov, R: CMov> {
+    rows: Vec<CndOption<(L, R)>>,
+    len: usize,
+}
+
+impl<L: CMov, R: CMov> JoinResult<L, R> {
+    /// Padded rows. Real rows come first, followed by `None`.
+    #[inline]
+    pub fn padded(&self) -> &[CndOption<(L, R)>] {
+        &self.rows
+    }
+
+    #[inline]
+    pub fn into_padded(self) -> Vec<CndOption<(L, R)>> {
+        self.rows
+    }
+
+    /// Reveal the real size of the join, which may exceed the padded length.
+    #[inline]
+    pub fn reveal_len(&self) -> usize {
+        self.len
+    }
+
+    /// Reveal the real rows. Rows beyond the padded length are dropped.
+    pub fn reveal(self) -> Vec<(L, R)> {
+        let len = self.len.min(self.rows.len());
+        self.rows
+            .into_iter()
+            .take(len)
+            .map(CndOption::unwrap_unchecked)
+            .collect()
+    }
+}
+
+/// Oblivious equi-join of `left` and `right` on keys extracted by `left_key` and `right_key`.
+///
+/// Rows are ordered by key, then by the index of the left row, then by the index of the right row.
+/// The result is padded to `output_len` rows, so the memory access pattern only depends on
+/// `left.len()`, `right.len()` and `output_len`. Only the first `output_len` rows are kept, and
+/// the caller can detect dropped rows through `JoinResult::reveal_len`.
+pub fn equi_join<K, L, R, FL, FR>(
+    left: &[L],
+    right: &[R],
+    mut left_key: FL,
+    mut right_key: FR,
+    output_len: usize,
+) -> JoinResult<L, R>
+where
+    K: CMov + Ord + Default,
+    L: CMov + Default,
+    R: CMov + Default,
+    FL: FnMut(&L) -> K,
+    FR: FnMut(&R) -> K,
+{
+    let mut rows: Vec<UnionRow<K, L, R>> = Vec::with_capacity(left.len() + right.len());
+    rows.extend(left.iter().enumerate().map(|(index, l)| UnionRow {
+        key: left_key(l),
+        is_right: false,
+        index,
+        left: l.clone(),
+        ..Default::default()
+    }));
+    rows.extend(right.iter().enumerate().map(|(index, r)| UnionRow {
+        key: right_key(r),
+        is_right: true,
+        index,
+        right: r.clone(),
+        ..Default::default()
+    }));
+    bitonic_sort_by(&mut rows, |a, b| {
+        a.key
+            .cmp(&b.key)
+            .then(a.is_right.cmp(&b.is_right))
+            .then(a.index.cmp(&b.index))
+    });
+
+    // Count right rows within every group of the same key, and before the group.
+    let mut right_count = 0;
+    let mut right_start = 0;
+    let mut num_right = 0;
+    for i in 0..rows.len() {
+        let new_group = i == 0 || rows[i].key != rows[i - 1].key;
+        right_count.cnd_assign(&0, new_group);
+        right_start.cnd_assign(&num_right, new_group);
+        right_count += rows[i].is_right as usize;
+        num_right += rows[i].is_right as usize;
+        rows[i].right_count = right_count;
+        rows[i].right_start = right_start;
+    }
+    for i in (0..rows.len()).rev() {
+        let last_of_group = i == rows.len() - 1 || rows[i].key != rows[i + 1].key;
+        right_count.cnd_assign(&rows[i].right_count, last_of_group);
+        rows[i].right_count = right_count;
+    }
+
+    let len = rows
+        .iter()
+        .map(|row| !row.is_right as usize * row.right_count)
+        .sum();
+
+    let mut left_rows = rows.clone();
+    compact_by(&mut left_rows, |row| !row.is_right);
+    left_rows.truncate(left.len());
+    let mut right_rows = rows;
+    compact_by(&mut right_rows, |row| row.is_right);
+    right_rows.truncate(right.len());
+
+    // Each left row is repeated for every matching right row, in the order of the join.
+    let left_expanded = expand(&left_rows, |row| row.right_count, output_len);
+
+    // The `j`-th copy of a left row pairs with the `j`-th right row of its group, which is
+    // gathered by sorting the requests of all slots together with the right rows.
+    let mut entries = Vec::with_capacity(right_rows.len() + output_len);
+    entries.extend(
+        right_rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, row)| GatherEntry {
+                index,
+                is_request: false,
+                slot: 0,
+                value: row.right,
+            }),
+    );
+    let mut run_start = 0;
+    for (slot, l) in left_expanded.iter().enumerate() {
+        let row = l.as_ref_unchecked();
+        let new_run = slot == 0 || row.index != left_expanded[slot - 1].as_ref_unchecked().index;
+        run_start.cnd_assign(&slot, new_run);
+        entries.push(GatherEntry {
+            index: usize::cnd_select(
+                &(row.right_start + slot - run_start),
+                &usize::MAX,
+                l.is_none(),
+            ),
+            is_request: true,
+            slot,
+            value: R::default(),
+        });
+    }
+    bitonic_sort_by(&mut entries, |a, b| {
+        a.index.cmp(&b.index).then(a.is_request.cmp(&b.is_request))
+    });
+    let mut carry = R::default();
+    for entry in entries.iter_mut() {
+        carry.cnd_assign(&entry.value, !entry.is_request);
+        entry.value.cnd_assign(&carry, entry.is_request);
+    }
+    compact_by(&mut entries, |entry| entry.is_request);
+    entries.truncate(output_len);
+    bitonic_sort_by(&mut entries, |a, b| a.slot.cmp(&b.slot));
+
+    let rows = left_expanded
+        .into_iter()
+        .zip(entries)
+        .map(|(l, r)| {
+            let is_some = l.is_some();
+            CndOption::new((l.unwrap_unchecked().left, r.value), is_some)
+        })
+        .collect();
+
+    JoinResult { rows, len }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn arb_table() -> impl Strategy<Value = Vec<(u8, u32)>> {
+        prop::collection::vec((0u8..6, any::<u32>()), 0..30)
+    }
+
+    /// Join in the order of `equi_join`.
+    fn nested_loop_join(left: &[(u8, u32)], right: &[(u8, u32)]) -> Vec<((u8, u32), (u8, u32))> {
+        let mut matches = Vec::new();
+        for (i, l) in left.iter().enumerate() {
+            for (j, r) in right.iter().enumerate() {
+                if l.0 == r.0 {
+                    matches.push((l.0, i, j));
+                }
+            }
+        }
+        matches.sort_unstable();
+        matches
+            .into_iter()
+            .map(|(_, i, j)| (left[i], right[j]))
+            .collect()
+    }
+
+    #[test]
+    fn test_equi_join_truncated_group() {
+        let left = [(1, 10), (1, 11), (1, 12)];
+        let right = [(1, 20), (1, 21), (1, 22)];
+        let res = equi_join(&left, &right, |l| l.0, |r| r.0, 4);
+        assert_eq!(
+            res.reveal(),
+            vec![
+                ((1, 10), (1, 20)),
+                ((1, 10), (1, 21)),
+                ((1, 10), (1, 22)),
+                ((1, 11), (1, 20)),
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_equi_join(left in arb_table(), right in arb_table()) {
+            let expected = nested_loop_join(&left, &right);
+
+            let output_len = left.len() * right.len();
+            let res = equi_join(&left, &right, |l| l.0, |r| r.0, output_len);
+            prop_assert_eq!(res.padded().len(), output_len);
+            prop_assert_eq!(res.reveal_len(), expected.len());
+            prop_assert_eq!(res.reveal(), expected);
+        }
+
+        #[test]
+        fn test_equi_join_truncated(left in arb_table(), right in arb_table(), output_len in 0usize..20) {
+            let expected = nested_loop_join(&left, &right);
+
+            let res = equi_join(&left, &right, |l| l.0, |r| r.0, output_len);
+            prop_assert_eq!(res.padded().len(), output_len);
+            prop_assert_eq!(res.reveal_len(), expected.len());
+            let kept = expected.len().min(output_len);
+            prop_assert_eq!(res.reveal(), &expected[..kept]);
+        }
+    }
+}
@@ -3,15 +3,31 @@
 #![feature(int_log)]
 #![feature(async_fn_in_trait)]
 #![feature(array_windows)]
+#![feature(avx512_target_feature)]
 #![allow(clippy::too_many_arguments)]
 #[macro_use]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+// Lets macros refer to this crate as `::hello_rust_core` inside it.
+extern crate self as hello_rust_core;
 
 pub use anyhow as error;
 
 pub mod sort;
+pub mod aggregate;
 pub mod aligned;
+pub mod api;
 pub mod cmov;
+pub mod compact;
+#[cfg(all(test, target_arch = "x86_64"))]
+pub mod dudect;
+pub mod ecall;
+pub mod join;
+pub mod omap;
+pub mod oram;
+#[cfg(any(test, feature = "trace"))]
+pub mod trace;
 pub mod util;
 
 mod example;
\ No newline at end of file
@@ -0,0 +1,244 @@
+//! Oblivious hash map based on two-choice hashing.
+//!
+//! Every key is hashed to two buckets of fixed size. Each operation scans both buckets and the
+//! whole stash with CMOV, so hits and misses touch exactly the same memory. Keys are compared by
+//! `CtOrd`, so that the comparison does not stop at the first differing byte.
+
+use crate::cmov::{CMov, CndOption, CtOrd};
+use alloc::vec::Vec;
+#[allow(deprecated)]
+use core::hash::SipHasher;
+use core::hash::{Hash, Hasher};
+use rand::RngCore;
+
+/// Number of entries per bucket.
+pub const BUCKET_SIZE: usize = 8;
+
+/// Default number of entries in the stash.
+pub const DEFAULT_STASH_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Default, CMov)]
+struct Entry<K: CMov, V: CMov> {
+    key: K,
+    value: V,
+    occupied: bool,
+}
+
+/// Oblivious hash map.
+///
+/// Only the two buckets of the queried key are revealed, regardless of whether the key exists.
+/// The number of entries is not kept, since updating it would reveal whether an insert added a
+/// key.
+pub struct ObliviousHashMap<K: CMov, V: CMov> {
+    buckets: Vec<Entry<K, V>>,
+    stash: Vec<Entry<K, V>>,
+    num_buckets: usize,
+    hash_keys: [(u64, u64); 2],
+}
+
+impl<K, V> ObliviousHashMap<K, V>
+where
+    K: CMov + Default + CtOrd + Hash,
+    V: CMov + Default,
+{
+    /// Create a map which holds about `capacity` entries at 50% load.
+    pub fn with_capacity<R: RngCore + ?Sized>(capacity: usize, rng: &mut R) -> Self {
+        let num_buckets = (capacity * 2 + BUCKET_SIZE - 1) / BUCKET_SIZE;
+        Self::with_buckets(num_buckets.max(1), DEFAULT_STASH_SIZE, rng)
+    }
+
+    /// Create a map with `num_buckets` buckets and a stash of `stash_size` entries.
+    pub fn with_buckets<R: RngCore + ?Sized>(
+        num_buckets: usize,
+        stash_size: usize,
+        rng: &mut R,
+    ) -> Self {
+        assert!(num_buckets > 0, "num_buckets should be non-zero");
+        let hash_keys = [
+            (rng.next_u64(), rng.next_u64()),
+            (rng.next_u64(), rng.next_u64()),
+        ];
+        Self {
+            buckets: vec![Entry::default(); num_buckets * BUCKET_SIZE],
+            stash: vec![Entry::default(); stash_size],
+            num_buckets,
+            hash_keys,
+        }
+    }
+
+    /// Number of entries in all buckets and the stash.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.buckets.len() + self.stash.len()
+    }
+
+    #[allow(deprecated)]
+    fn bucket_range(&self, key: &K, which: usize) -> core::ops::Range<usize> {
+        let (k0, k1) = self.hash_keys[which];
+        let mut hasher = SipHasher::new_with_keys(k0, k1);
+        key.hash(&mut hasher);
+        let start = (hasher.finish() % self.num_buckets as u64) as usize * BUCKET_SIZE;
+        start..start + BUCKET_SIZE
+    }
+
+    /// Scan the two buckets of `key` and the stash.
+    #[inline]
+    fn scan<F>(&mut self, key: &K, mut f: F)
+    where
+        F: FnMut(usize, &mut Entry<K, V>),
+    {
+        let first = self.bucket_range(key, 0);
+        let second = self.bucket_range(key, 1);
+        // Scan both buckets even if they are the same one.
+        for (i, idx) in first.chain(second).enumerate() {
+            f(i, &mut self.buckets[idx]);
+        }
+        for (i, entry) in self.stash.iter_mut().enumerate() {
+            f(2 * BUCKET_SIZE + i, entry);
+        }
+    }
+
+    /// Get the value of `key`.
+    pub fn get(&self, key: &K) -> CndOption<V> {
+        let first = self.bucket_range(key, 0);
+        let second = self.bucket_range(key, 1);
+        let entries = self.buckets[first]
+            .iter()
+            .chain(&self.buckets[second])
+            .chain(&self.stash);
+        let mut ret = CndOption::new_none();
+        for entry in entries {
+            let hit = entry.occupied & entry.key.ct_eq(key);
+            ret.cnd_assign(&CndOption::new_some(entry.value.clone()), hit);
+        }
+        ret
+    }
+
+    /// Insert `key` with `value` and return the old value.
+    ///
+    /// Panic if both buckets and the stash are full.
+    pub fn insert(&mut self, key: K, value: V) -> CndOption<V> {
+        let mut ret = CndOption::new_none();
+        let mut load = [0usize; 2];
+        self.scan(&key, |i, entry| {
+            let hit = entry.occupied & entry.key.ct_eq(&key);
+            // The two buckets may be the same one, so keep the value seen at the first hit.
+            ret.cnd_assign(
+                &CndOption::new_some(entry.value.clone()),
+                hit & ret.is_none(),
+            );
+            entry.value.cnd_assign(&value, hit);
+            if i < 2 * BUCKET_SIZE {
+                load[i / BUCKET_SIZE] += entry.occupied as usize;
+            }
+        });
+
+        // Place a new entry into the less loaded bucket, or the stash if both are full.
+        let found = ret.is_some();
+        let use_first = load[0] <= load[1];
+        let new_entry = Entry {
+            key,
+            value,
+            occupied: true,
+        };
+        let mut placed = found;
+        let key = new_entry.key.clone();
+        self.scan(&key, |i, entry| {
+            let preferred = match i / BUCKET_SIZE {
+                0 => use_first,
+                1 => !use_first,
+                _ => true,
+            };
+            let choice = !placed & !entry.occupied & preferred;
+            entry.cnd_assign(&new_entry, choice);
+            placed |= choice;
+        });
+        self.scan(&key, |_, entry| {
+            let choice = !placed & !entry.occupied;
+            entry.cnd_assign(&new_entry, choice);
+            placed |= choice;
+        });
+        assert!(placed, "stash overflow");
+        ret
+    }
+
+    /// Remove `key` and return its value.
+    pub fn remove(&mut self, key: &K) -> CndOption<V> {
+        let mut ret = CndOption::new_none();
+        self.scan(key, |_, entry| {
+            let hit = entry.occupied & entry.key.ct_eq(key);
+            ret.cnd_assign(&CndOption::new_some(entry.value.clone()), hit);
+            entry.occupied.cnd_assign(&false, hit);
+        });
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::collections::HashMap;
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Get(u32),
+        Insert(u32, u64),
+        Remove(u32),
+    }
+
+    fn arb_op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (0u32..64).prop_map(Op::Get),
+            (0u32..64, any::<u64>()).prop_map(|(k, v)| Op::Insert(k, v)),
+            (0u32..64).prop_map(Op::Remove),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_omap(ops in prop::collection::vec(arb_op(), 0..500), seed in any::<u64>()) {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut map = ObliviousHashMap::with_capacity(64, &mut rng);
+            let mut expected = HashMap::new();
+            for op in ops {
+                let (res, expected_res): (Option<u64>, _) = match op {
+                    Op::Get(k) => (map.get(&k).into(), expected.get(&k).copied()),
+                    Op::Insert(k, v) => (map.insert(k, v).into(), expected.insert(k, v)),
+                    Op::Remove(k) => (map.remove(&k).into(), expected.remove(&k)),
+                };
+                prop_assert_eq!(res, expected_res);
+            }
+        }
+    }
+
+    /// Inserting a key twice replaces the value instead of adding a second entry.
+    #[test]
+    fn test_insert_duplicate() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut map = ObliviousHashMap::with_capacity(64, &mut rng);
+        assert!(map.insert(55u32, 0u64).is_none());
+        assert_eq!(Option::from(map.insert(55, 1)), Some(0));
+        assert_eq!(Option::from(map.get(&55)), Some(1));
+        assert_eq!(Option::from(map.remove(&55)), Some(1));
+        assert!(map.get(&55).is_none());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let map = ObliviousHashMap::<u32, u64>::with_buckets(4, 3, &mut rng);
+        assert_eq!(map.capacity(), 4 * BUCKET_SIZE + 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "stash overflow")]
+    fn test_overflow() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut map = ObliviousHashMap::with_buckets(1, 1, &mut rng);
+        for k in 0..=BUCKET_SIZE as u32 + 1 {
+            map.insert(k, 0u64);
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! Path ORAM.
+//! Ref: Stefanov et al. Path ORAM: An Extremely Simple Oblivious RAM Protocol. CCS 2013.
+
+use crate::{
+    aligned::{AlignedBox, A64},
+    cmov::CMov,
+};
+use alloc::vec::Vec;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// ORAM block, which is moved by `cmov_bytes_a64`.
+pub type Block = AlignedBox<A64, [u8]>;
+
+/// Number of slots per bucket.
+pub const BUCKET_SIZE: usize = 4;
+
+/// Default number of slots in the stash.
+pub const DEFAULT_STASH_SIZE: usize = 64;
+
+const EMPTY_IDX: u64 = u64::MAX;
+
+/// A slot in a bucket or the stash.
+#[derive(Debug, Clone, CMov, Serialize, Deserialize)]
+pub struct Slot {
+    /// Block index, or `u64::MAX` if the slot is empty.
+    pub idx: u64,
+    /// Leaf the block is mapped to.
+    pub leaf: u64,
+    pub block: Block,
+}
+
+impl Slot {
+    #[inline]
+    pub fn new_empty(block: Block) -> Self {
+        Self {
+            idx: EMPTY_IDX,
+            leaf: 0,
+            block,
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.idx == EMPTY_IDX
+    }
+}
+
+/// Path ORAM over fixed-size blocks.
+///
+/// Every access reads and writes back one random path of the bucket tree, and scans the whole
+/// stash with CMOV, so that the accessed index is hidden.
+pub struct PathOram<S, P, R> {
+    storage: S,
+    position_map: P,
+    rng: R,
+    block_size: usize,
+    height: u32,
+    stash_size: usize,
+    /// The first `stash_size` slots are the stash. The rest hold the path being accessed.
+    stash: Vec<Slot>,
+    bucket: Vec<Slot>,
+}
+
+impl<R: RngCore> PathOram<VecStorage, LinearPositionMap, R> {
+    /// Create an in-memory ORAM with `len` zeroed blocks of `block_size` bytes.
+    pub fn new(len: usize, block_size: usize, mut rng: R) -> Self {
+        let height = Self::height_for(len);
+        let storage = VecStorage::new((2 << height) - 1, block_size);
+        let leaves = (0..len).map(|_| rng.gen_range(0..1 << height)).collect();
+        let position_map = LinearPositionMap::new(leaves);
+        Self::with_storage(storage, position_map, block_size, DEFAULT_STASH_SIZE, rng)
+    }
+}
+
+impl<S, P, R> PathOram<S, P, R>
+where
+    S: OramStorage,
+    P: PositionMap,
+    R: RngCore,
+{
+    /// Height of the bucket tree for `len` blocks. The tree has `1 << height` leaves.
+    #[inline]
+    pub fn height_for(len: usize) -> u32 {
+        len.max(1).next_power_of_two().ilog2()
+    }
+
+    /// Create an ORAM with custom storage and position map.
+    ///
+    /// The storage should have `(2 << height) - 1` buckets with empty slots, where `height` is
+    /// `Self::height_for(position_map.len())`. The position map should map blocks to random leaves.
+    pub fn with_storage(
+        storage: S,
+        position_map: P,
+        block_size: usize,
+        stash_size: usize,
+        rng: R,
+    ) -> Self {
+        let height = Self::height_for(position_map.len());
+        assert_eq!(
+            storage.num_buckets(),
+            (2 << height) - 1,
+            "storage size does not match the tree height"
+        );
+        let empty = Slot::new_empty(Block::from(vec![0u8; block_size]));
+        let path_size = BUCKET_SIZE * (height as usize + 1);
+        Self {
+            storage,
+            position_map,
+            rng,
+            block_size,
+            height,
+            stash_size,
+            stash: vec![empty.clone(); stash_size + path_size],
+            bucket: vec![empty; BUCKET_SIZE],
+        }
+    }
+
+    /// Number of blocks.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.position_map.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.position_map.is_empty()
+    }
+
+    /// Size of each block in bytes.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Number of occupied stash slots. Only for diagnostics, since it is not oblivious.
+    pub fn stash_occupancy(&self) -> usize {
+        self.stash[..self.stash_size]
+            .iter()
+            .filter(|slot| !slot.is_empty())
+            .count()
+    }
+
+    #[inline]
+    fn bucket_idx(&self, leaf: u64, level: u32) -> usize {
+        (((leaf + (1 << self.height)) >> (self.height - level)) - 1) as usize
+    }
+
+    /// Access block `idx` with `f`. Blocks which are never written are zeroed.
+    ///
+    /// Panic if `idx` is out of range or the stash overflows.
+    pub fn access<F, U>(&mut self, idx: usize, f: F) -> U
+    where
+        F: FnOnce(&mut Block) -> U,
+    {
+        assert!(idx < self.len(), "index out of range");
+        let new_leaf = self.rng.gen_range(0..1 << self.height);
+        let leaf = self.position_map.access_position(idx, new_leaf);
+        let idx = idx as u64;
+
+        // Read the path into the stash.
+        for level in 0..=self.height {
+            let bucket_idx = self.bucket_idx(leaf, level);
+            let start = self.stash_size + BUCKET_SIZE * level as usize;
+            self.storage
+                .read_bucket(bucket_idx, &mut self.stash[start..start + BUCKET_SIZE]);
+        }
+
+        // Take the block out of the stash.
+        let mut target = Slot::new_empty(Block::from(vec![0u8; self.block_size]));
+        for slot in self.stash.iter_mut() {
+            let hit = slot.idx == idx;
+            target.block.cnd_assign(&slot.block, hit);
+            slot.idx.cnd_assign(&EMPTY_IDX, hit);
+        }
+
+        let ret = f(&mut target.block);
+        assert_eq!(target.block.len(), self.block_size, "block size changed");
+        target.idx = idx;
+        target.leaf = new_leaf;
+
+        // Put the block back to the stash.
+        let mut inserted = false;
+        for slot in self.stash.iter_mut() {
+            let choice = !inserted & slot.is_empty();
+            slot.cnd_assign(&target, choice);
+            inserted |= choice;
+        }
+        assert!(inserted, "stash overflow");
+
+        // Evict blocks along the path, from the leaf to the root.
+        for level in (0..=self.height).rev() {
+            let shift = self.height - level;
+            for bucket_slot in self.bucket.iter_mut() {
+                bucket_slot.idx = EMPTY_IDX;
+                let mut filled = false;
+                for slot in self.stash.iter_mut() {
+                    let fits = (slot.leaf ^ leaf) >> shift == 0;
+                    let choice = !filled & !slot.is_empty() & fits;
+                    <_ as CMov>::cnd_swap(bucket_slot, slot, choice);
+                    filled |= choice;
+                }
+            }
+            let bucket_idx = self.bucket_idx(leaf, level);
+            self.storage.write_bucket(bucket_idx, &self.bucket);
+        }
+
+        // Move the remaining blocks of the path into the stash.
+        let (stash, path) = self.stash.split_at_mut(self.stash_size);
+        let mut overflow = false;
+        for path_slot in path.iter_mut() {
+            for slot in stash.iter_mut() {
+                let choice = !path_slot.is_empty() & slot.is_empty();
+                <_ as CMov>::cnd_swap(path_slot, slot, choice);
+            }
+            overflow |= !path_slot.is_empty();
+        }
+        assert!(!overflow, "stash overflow");
+
+        ret
+    }
+
+    /// Read block `idx`.
+    #[inline]
+    pub fn read(&mut self, idx: usize) -> Block {
+        self.access(idx, |block| block.clone())
+    }
+
+    /// Write block `idx`.
+    ///
+    /// Panic if the size of `block` does not match.
+    #[inline]
+    pub fn write(&mut self, idx: usize, block: &Block) {
+        assert_eq!(block.len(), self.block_size, "block size does not match");
+        self.access(idx, |dst| dst.copy_from_slice(block))
+    }
+}
+
+mod position_map;
+pub use position_map::*;
+
+mod storage;
+pub use storage::*;
+
+#[cfg(test)]
+mod tests;
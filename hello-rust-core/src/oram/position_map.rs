@@ -0,0 +1,46 @@
+use crate::cmov::CMov;
+use alloc::vec::Vec;
+
+/// Trait for the position map of ORAM, which maps a block index to its leaf.
+pub trait PositionMap {
+    /// Number of blocks.
+    fn len(&self) -> usize;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Set the leaf of block `idx` to `new_leaf` and return the old one, obliviously.
+    fn access_position(&mut self, idx: usize, new_leaf: u64) -> u64;
+}
+
+/// Position map which scans the whole array on every access. It costs O(n) per access.
+#[derive(Debug, Clone)]
+pub struct LinearPositionMap {
+    leaves: Vec<u64>,
+}
+
+impl LinearPositionMap {
+    #[inline]
+    pub fn new(leaves: Vec<u64>) -> Self {
+        Self { leaves }
+    }
+}
+
+impl PositionMap for LinearPositionMap {
+    #[inline]
+    fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    fn access_position(&mut self, idx: usize, new_leaf: u64) -> u64 {
+        let mut old_leaf = 0;
+        for (i, leaf) in self.leaves.iter_mut().enumerate() {
+            let hit = i == idx;
+            old_leaf.cnd_assign(leaf, hit);
+            leaf.cnd_assign(&new_leaf, hit);
+        }
+        old_leaf
+    }
+}
@@ -0,0 +1,55 @@
+use super::{Block, Slot, BUCKET_SIZE};
+use alloc::vec::Vec;
+
+/// Trait for the storage of the ORAM bucket tree.
+///
+/// Buckets are indexed in heap order, i.e., the root is `0` and the children of bucket `i` are
+/// `2 * i + 1` and `2 * i + 2`. Every bucket holds `BUCKET_SIZE` slots.
+///
+/// The ORAM only reveals which buckets are accessed, so the storage may live in untrusted memory.
+/// In that case, the implementation is responsible for encrypting the slots.
+pub trait OramStorage {
+    /// Number of buckets.
+    fn num_buckets(&self) -> usize;
+
+    /// Read the bucket `bucket_idx` into `out`, whose length is `BUCKET_SIZE`.
+    fn read_bucket(&mut self, bucket_idx: usize, out: &mut [Slot]);
+
+    /// Write `slots`, whose length is `BUCKET_SIZE`, to the bucket `bucket_idx`.
+    fn write_bucket(&mut self, bucket_idx: usize, slots: &[Slot]);
+}
+
+/// Bucket tree stored in a `Vec`.
+#[derive(Debug, Clone)]
+pub struct VecStorage {
+    slots: Vec<Slot>,
+}
+
+impl VecStorage {
+    /// Create a storage with `num_buckets` empty buckets of blocks with `block_size` bytes.
+    pub fn new(num_buckets: usize, block_size: usize) -> Self {
+        let empty = Slot::new_empty(Block::from(vec![0u8; block_size]));
+        Self {
+            slots: vec![empty; num_buckets * BUCKET_SIZE],
+        }
+    }
+}
+
+impl OramStorage for VecStorage {
+    #[inline]
+    fn num_buckets(&self) -> usize {
+        self.slots.len() / BUCKET_SIZE
+    }
+
+    #[inline]
+    fn read_bucket(&mut self, bucket_idx: usize, out: &mut [Slot]) {
+        let start = bucket_idx * BUCKET_SIZE;
+        out.clone_from_slice(&self.slots[start..start + BUCKET_SIZE]);
+    }
+
+    #[inline]
+    fn write_bucket(&mut self, bucket_idx: usize, slots: &[Slot]) {
+        let start = bucket_idx * BUCKET_SIZE;
+        self.slots[start..start + BUCKET_SIZE].clone_from_slice(slots);
+    }
+}
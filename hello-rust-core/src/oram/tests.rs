@@ -0,0 +1,70 @@
+use super::*;
+use proptest::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+#[derive(Debug, Clone)]
+enum Op {
+    Read(usize),
+    Write(usize, u8),
+}
+
+fn arb_ops(len: usize) -> impl Strategy<Value = Vec<Op>> {
+    let op = prop_oneof![
+        (0..len).prop_map(Op::Read),
+        (0..len, any::<u8>()).prop_map(|(idx, v)| Op::Write(idx, v)),
+    ];
+    prop::collection::vec(op, 0..200)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig { cases: 32, ..Default::default() })]
+
+    #[test]
+    fn test_path_oram(
+        (len, ops) in (1usize..100).prop_flat_map(|len| (Just(len), arb_ops(len))),
+        block_size in 1usize..100,
+        seed in any::<u64>(),
+    ) {
+        let mut oram = PathOram::new(len, block_size, StdRng::seed_from_u64(seed));
+        let mut expected = vec![vec![0u8; block_size]; len];
+        for op in ops {
+            match op {
+                Op::Read(idx) => {
+                    prop_assert_eq!(&oram.read(idx)[..], &expected[idx][..]);
+                }
+                Op::Write(idx, v) => {
+                    let block = Block::from(vec![v; block_size]);
+                    oram.write(idx, &block);
+                    expected[idx] = vec![v; block_size];
+                }
+            }
+        }
+        for (idx, expected) in expected.iter().enumerate() {
+            prop_assert_eq!(&oram.read(idx)[..], &expected[..]);
+        }
+    }
+}
+
+#[test]
+fn test_access() {
+    let len = 200;
+    let mut oram = PathOram::new(len, 64, StdRng::seed_from_u64(0));
+    for round in 0..3u64 {
+        for idx in 0..len {
+            let old = oram.access(idx, |block| {
+                let old = u64::from_le_bytes(block[..8].try_into().unwrap());
+                block[..8].copy_from_slice(&(old + idx as u64).to_le_bytes());
+                old
+            });
+            assert_eq!(old, round * idx as u64);
+        }
+    }
+    assert!(oram.stash_occupancy() < DEFAULT_STASH_SIZE);
+}
+
+#[test]
+#[should_panic(expected = "index out of range")]
+fn test_out_of_range() {
+    let mut oram = PathOram::new(10, 8, StdRng::seed_from_u64(0));
+    oram.read(10);
+}
@@ -1,20 +1,26 @@
 //! Bitonic Sort.
 //! Ref: <https://www.inf.hs-flensburg.de/lang/algorithmen/sortieren/bitonic/oddn.htm>
 
-use crate::cmov::CMov;
+use crate::cmov::{CMov, CndOption, CtOrd};
 use alloc::vec::Vec;
 use core::cmp::Ordering;
+use rand::RngCore;
 
 #[inline(always)]
-unsafe fn compare_and_swap<T, F>(array: &mut [T], i: usize, j: usize, cmp: &mut F, ascending: bool)
-where
+unsafe fn compare_and_swap<T, F>(
+    array: &mut [T],
+    i: usize,
+    j: usize,
+    is_less: &mut F,
+    ascending: bool,
+) where
     T: CMov,
-    F: FnMut(&T, &T) -> Ordering,
+    F: FnMut(&T, &T) -> bool,
 {
     let ptr = array.as_mut_ptr();
     let a = &mut *ptr.add(i);
     let b = &mut *ptr.add(j);
-    let choice = (cmp(a, b) == Ordering::Less) != ascending;
+    let choice = is_less(a, b) != ascending;
     <_ as CMov>::cnd_swap(a, b, choice);
 }
 
@@ -22,20 +28,20 @@ unsafe fn bitonic_merge_inner<T, F>(
     array: &mut [T],
     start: usize,
     len: usize,
-    cmp: &mut F,
+    is_less: &mut F,
     ascending: bool,
 ) where
     T: CMov,
-    F: FnMut(&T, &T) -> Ordering,
+    F: FnMut(&T, &T) -> bool,
 {
     if len > 1 {
         let first_half = len.next_power_of_two() / 2;
         let second_half = len - first_half;
         for i in (start..).take(second_half) {
-            compare_and_swap(array, i, i + first_half, cmp, ascending);
+            compare_and_swap(array, i, i + first_half, is_less, ascending);
         }
-        bitonic_merge_inner(array, start, first_half, cmp, ascending);
-        bitonic_merge_inner(array, start + first_half, second_half, cmp, ascending);
+        bitonic_merge_inner(array, start, first_half, is_less, ascending);
+        bitonic_merge_inner(array, start + first_half, second_half, is_less, ascending);
     }
 }
 
@@ -43,17 +49,17 @@ unsafe fn bitonic_sort_inner<T, F>(
     array: &mut [T],
     start: usize,
     len: usize,
-    cmp: &mut F,
+    is_less: &mut F,
     ascending: bool,
 ) where
     T: CMov,
-    F: FnMut(&T, &T) -> Ordering,
+    F: FnMut(&T, &T) -> bool,
 {
     if len > 1 {
         let half = len / 2;
-        bitonic_sort_inner(array, start, half, cmp, !ascending);
-        bitonic_sort_inner(array, start + half, len - half, cmp, ascending);
-        bitonic_merge_inner(array, start, len, cmp, ascending);
+        bitonic_sort_inner(array, start, half, is_less, !ascending);
+        bitonic_sort_inner(array, start + half, len - half, is_less, ascending);
+        bitonic_merge_inner(array, start, len, is_less, ascending);
     }
 }
 
@@ -64,7 +70,8 @@ where
     T: CMov,
     F: FnMut(&T, &T) -> Ordering,
 {
-    unsafe { bitonic_sort_inner(array, 0, array.len(), &mut cmp, true) }
+    let mut is_less = |a: &T, b: &T| cmp(a, b) == Ordering::Less;
+    unsafe { bitonic_sort_inner(array, 0, array.len(), &mut is_less, true) }
 }
 
 /// Bitonic sort by key.
@@ -87,6 +94,104 @@ where
     bitonic_sort_by(array, |a, b| a.cmp(b))
 }
 
+/// Bitonic sort by key with constant-time comparison.
+#[inline]
+pub fn bitonic_sort_ct_by_key<T, F, K>(array: &mut [T], mut f: F)
+where
+    T: CMov,
+    F: FnMut(&T) -> K,
+    K: CtOrd,
+{
+    let mut is_less = |a: &T, b: &T| f(a).ct_lt(&f(b));
+    unsafe { bitonic_sort_inner(array, 0, array.len(), &mut is_less, true) }
+}
+
+/// Bitonic sort with constant-time comparison.
+#[inline]
+pub fn bitonic_sort_ct<T>(array: &mut [T])
+where
+    T: CMov + CtOrd,
+{
+    let mut is_less = |a: &T, b: &T| a.ct_lt(b);
+    unsafe { bitonic_sort_inner(array, 0, array.len(), &mut is_less, true) }
+}
+
+/// Bitonic sort by custom cmp function, padded to a public upper bound.
+///
+/// The input is padded with `CndOption::new_none()` dummies up to `max_len.next_power_of_two()`,
+/// which are sorted after all real elements. Hence the sorting network only depends on `max_len`
+/// rather than `array.len()`.
+///
+/// Panic if `array.len() > max_len`.
+pub fn bitonic_sort_padded_by<T, F>(array: &mut [T], max_len: usize, mut cmp: F)
+where
+    T: CMov + Default,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    assert!(
+        array.len() <= max_len,
+        "input length exceeds the public upper bound"
+    );
+
+    let padded_len = max_len.next_power_of_two();
+    let mut padded: Vec<CndOption<T>> = Vec::with_capacity(padded_len);
+    padded.extend(array.iter().cloned().map(CndOption::new_some));
+    padded.resize_with(padded_len, CndOption::new_none);
+
+    // Dummies are greater than any real element. Always evaluate `cmp` to avoid branching.
+    let mut padded_is_less = |a: &CndOption<T>, b: &CndOption<T>| {
+        let ord = cmp(a.as_ref_unchecked(), b.as_ref_unchecked());
+        a.is_none().cmp(&b.is_none()).then(ord) == Ordering::Less
+    };
+    unsafe { bitonic_sort_inner(&mut padded, 0, padded_len, &mut padded_is_less, true) }
+
+    for (dst, src) in array.iter_mut().zip(padded) {
+        *dst = src.unwrap_unchecked();
+    }
+}
+
+/// Bitonic sort by key, padded to a public upper bound.
+#[inline]
+pub fn bitonic_sort_padded_by_key<T, F, K>(array: &mut [T], max_len: usize, mut f: F)
+where
+    T: CMov + Default,
+    F: FnMut(&T) -> K,
+    K: Ord,
+{
+    bitonic_sort_padded_by(array, max_len, |a, b| f(a).cmp(&f(b)))
+}
+
+/// Bitonic sort, padded to a public upper bound.
+#[inline]
+pub fn bitonic_sort_padded<T>(array: &mut [T], max_len: usize)
+where
+    T: CMov + Default + Ord,
+{
+    bitonic_sort_padded_by(array, max_len, |a, b| a.cmp(b))
+}
+
+/// Oblivious random shuffle.
+///
+/// Each item is tagged with a random 128-bit key drawn from `rng` and the array is bitonic-sorted by
+/// the tags, so that the resulting permutation is uniform except for negligible tag collisions.
+/// The tags are compared in constant time, since the swap decisions reveal the permutation.
+pub fn oblivious_shuffle<T, R>(array: &mut [T], rng: &mut R)
+where
+    T: CMov,
+    R: RngCore + ?Sized,
+{
+    let mut tagged: Vec<((u64, u64), T)> = array
+        .iter()
+        .cloned()
+        .map(|item| ((rng.next_u64(), rng.next_u64()), item))
+        .collect();
+    bitonic_sort_ct_by_key(&mut tagged, |item| item.0);
+
+    for (dst, (_, src)) in array.iter_mut().zip(tagged) {
+        *dst = src;
+    }
+}
+
 #[allow(dead_code)]
 fn array_is_sorted_by<T, F>(array: &[T], mut cmp: F) -> bool
 where
@@ -135,8 +240,8 @@ pub fn bitonic_merge_sorted_iters<T: CMov + Ord>(
         "right is not sorted"
     );
 
-    let mut cmp = |a: &T, b: &T| a.cmp(b);
-    unsafe { bitonic_merge_inner(&mut array, 0, total_len, &mut cmp, true) }
+    let mut is_less = |a: &T, b: &T| a < b;
+    unsafe { bitonic_merge_inner(&mut array, 0, total_len, &mut is_less, true) }
 
     array
 }
@@ -167,11 +272,17 @@ pub fn bitonic_merge_sorted_slices<T: CMov + Ord>(left: &[T], right: &[T]) -> Ve
     array.extend(left.iter().rev().cloned());
     array.extend_from_slice(right);
 
-    let mut cmp = |a: &T, b: &T| a.cmp(b);
-    unsafe { bitonic_merge_inner(&mut array, 0, len, &mut cmp, true) }
+    let mut is_less = |a: &T, b: &T| a < b;
+    unsafe { bitonic_merge_inner(&mut array, 0, len, &mut is_less, true) }
 
     array
 }
 
+mod network;
+pub use network::*;
+
+mod parallel;
+pub use parallel::*;
+
 #[cfg(test)]
 mod tests;
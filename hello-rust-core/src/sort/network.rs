@@ -0,0 +1,281 @@
+//! Bitonic sorting network, whose comparators are generated lazily stage by stage.
+
+use super::compare_and_swap;
+use crate::cmov::CMov;
+use alloc::vec::Vec;
+use core::{cmp::Ordering, ops::Range};
+
+/// A comparator `(i, j, ascending)` with `i < j`.
+pub type Comparator = (usize, usize, bool);
+
+/// Comparators `(start + k, start + k + distance, ascending)` for `k < count`.
+#[derive(Debug, Clone, Copy)]
+struct Run {
+    start: usize,
+    count: usize,
+    distance: usize,
+    ascending: bool,
+}
+
+impl Run {
+    #[inline]
+    fn comparators(self, range: Range<usize>) -> impl Iterator<Item = Comparator> {
+        range.map(move |k| {
+            let i = self.start + k;
+            (i, i + self.distance, self.ascending)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Node {
+    Sort {
+        start: usize,
+        len: usize,
+        ascending: bool,
+        level: usize,
+    },
+    Merge {
+        start: usize,
+        len: usize,
+        ascending: bool,
+        depth: usize,
+    },
+}
+
+/// Runs of a stage, found by walking the recursion of bitonic sort down to the stage.
+struct Runs {
+    stage: Stage,
+    nodes: Vec<Node>,
+}
+
+impl Iterator for Runs {
+    type Item = Run;
+
+    fn next(&mut self) -> Option<Run> {
+        while let Some(node) = self.nodes.pop() {
+            match node {
+                Node::Sort {
+                    start,
+                    len,
+                    ascending,
+                    level,
+                } if len > 1 => {
+                    if level == self.stage.level {
+                        self.nodes.push(Node::Merge {
+                            start,
+                            len,
+                            ascending,
+                            depth: 0,
+                        });
+                    } else {
+                        let half = len / 2;
+                        self.nodes.push(Node::Sort {
+                            start: start + half,
+                            len: len - half,
+                            ascending,
+                            level: level + 1,
+                        });
+                        self.nodes.push(Node::Sort {
+                            start,
+                            len: half,
+                            ascending: !ascending,
+                            level: level + 1,
+                        });
+                    }
+                }
+                Node::Merge {
+                    start,
+                    len,
+                    ascending,
+                    depth,
+                } if len > 1 => {
+                    let first_half = len.next_power_of_two() / 2;
+                    let second_half = len - first_half;
+                    if depth == self.stage.depth {
+                        return Some(Run {
+                            start,
+                            count: second_half,
+                            distance: first_half,
+                            ascending,
+                        });
+                    }
+                    self.nodes.push(Node::Merge {
+                        start: start + first_half,
+                        len: second_half,
+                        ascending,
+                        depth: depth + 1,
+                    });
+                    self.nodes.push(Node::Merge {
+                        start,
+                        len: first_half,
+                        ascending,
+                        depth: depth + 1,
+                    });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// A stage of a sorting network.
+///
+/// It consists of the merge steps at `depth` of all sub-sorts at recursion `level`. Sub-sorts at
+/// the same level are disjoint, so comparators within a stage touch disjoint indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    len: usize,
+    level: usize,
+    depth: usize,
+}
+
+impl Stage {
+    fn runs(&self) -> Runs {
+        let mut nodes = Vec::with_capacity(2 * (self.level + self.depth) + 1);
+        nodes.push(Node::Sort {
+            start: 0,
+            len: self.len,
+            ascending: true,
+            level: 0,
+        });
+        Runs {
+            stage: *self,
+            nodes,
+        }
+    }
+
+    /// Number of comparators.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.runs().map(|run| run.count).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.runs().next().is_none()
+    }
+
+    /// Iterate over comparators.
+    #[inline]
+    pub fn comparators(&self) -> impl Iterator<Item = Comparator> {
+        self.runs().flat_map(|run| run.comparators(0..run.count))
+    }
+
+    /// Iterate over comparators whose position within the stage is in `range`.
+    ///
+    /// Comparators before `range` are skipped run by run, instead of one by one.
+    pub fn comparators_in(&self, range: Range<usize>) -> impl Iterator<Item = Comparator> {
+        let mut offset = 0;
+        self.runs().flat_map(move |run| {
+            let start = range.start.clamp(offset, offset + run.count) - offset;
+            let end = range.end.clamp(offset, offset + run.count) - offset;
+            offset += run.count;
+            run.comparators(start..end)
+        })
+    }
+}
+
+/// Reusable comparator schedule of bitonic sort for a fixed length.
+///
+/// Comparators are grouped into stages. Comparators within the same stage touch disjoint indices,
+/// so they are independent of each other. Stages are generated on demand, so the network only
+/// takes `O(log n)` memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortingNetwork {
+    len: usize,
+}
+
+impl SortingNetwork {
+    /// The bitonic sorting network for arrays of length `len`.
+    ///
+    /// The network performs exactly the same comparators as `bitonic_sort_by`, regrouped into
+    /// stages.
+    #[inline]
+    pub fn bitonic(len: usize) -> Self {
+        Self { len }
+    }
+
+    /// Length of arrays this network sorts.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of stages.
+    #[inline]
+    pub fn num_stages(&self) -> usize {
+        self.stages().count()
+    }
+
+    /// Iterate over all comparators, ordered by stage.
+    #[inline]
+    pub fn comparators(&self) -> impl Iterator<Item = Comparator> {
+        self.stages().flat_map(|stage| stage.comparators())
+    }
+
+    /// Iterate over stages.
+    ///
+    /// Sub-sorts are merged from the deepest recursion level up, and sub-sorts at the same level
+    /// are merged together.
+    pub fn stages(&self) -> impl Iterator<Item = Stage> {
+        let len = self.len;
+        // Sub-sorts at `level` have at most `ceil(len / 2^level)` elements.
+        let num_levels = if len > 1 {
+            len.next_power_of_two().trailing_zeros() as usize
+        } else {
+            0
+        };
+        (0..num_levels).rev().flat_map(move |level| {
+            (0..)
+                .map(move |depth| Stage { len, level, depth })
+                .take_while(|stage| !stage.is_empty())
+        })
+    }
+
+    /// Sort `array` by custom cmp function with this network.
+    ///
+    /// Panic if `array.len()` does not match the network length.
+    pub fn sort_by<T, F>(&self, array: &mut [T], mut cmp: F)
+    where
+        T: CMov,
+        F: FnMut(&T, &T) -> Ordering,
+    {
+        assert_eq!(
+            array.len(),
+            self.len,
+            "input length does not match the sorting network"
+        );
+        let mut is_less = |a: &T, b: &T| cmp(a, b) == Ordering::Less;
+        for (i, j, ascending) in self.comparators() {
+            // SAFETY: `i < j < array.len()`.
+            unsafe { compare_and_swap(array, i, j, &mut is_less, ascending) }
+        }
+    }
+
+    /// Sort `array` by key with this network.
+    #[inline]
+    pub fn sort_by_key<T, F, K>(&self, array: &mut [T], mut f: F)
+    where
+        T: CMov,
+        F: FnMut(&T) -> K,
+        K: Ord,
+    {
+        self.sort_by(array, |a, b| f(a).cmp(&f(b)))
+    }
+
+    /// Sort `array` with this network.
+    #[inline]
+    pub fn sort<T>(&self, array: &mut [T])
+    where
+        T: CMov + Ord,
+    {
+        self.sort_by(array, |a, b| a.cmp(b))
+    }
+}
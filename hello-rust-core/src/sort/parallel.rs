@@ -0,0 +1,231 @@
+//! Multi-threaded bitonic sort.
+
+use super::SortingNetwork;
+use crate::cmov::CMov;
+use core::{
+    cmp::Ordering,
+    hint, mem,
+    sync::atomic::{self, AtomicBool, AtomicUsize},
+};
+
+/// Trait for running workers on concurrent threads.
+pub trait WorkerThreads: Sync {
+    /// Number of workers.
+    fn num_threads(&self) -> usize;
+
+    /// Run `f(worker_id)` for every `worker_id` in `0..self.num_threads()` and wait for all of
+    /// them to finish.
+    ///
+    /// All workers must run concurrently, since they synchronize with each other.
+    fn broadcast<F>(&self, f: F)
+    where
+        F: Fn(usize) + Sync;
+
+    /// Called repeatedly while a worker waits for the others to finish the current stage.
+    #[inline]
+    fn relax(&self) {
+        hint::spin_loop();
+    }
+}
+
+/// Workers backed by `std::thread`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy)]
+pub struct StdWorkerThreads {
+    num_threads: usize,
+}
+
+#[cfg(feature = "std")]
+impl StdWorkerThreads {
+    /// Panic if `num_threads` is zero.
+    #[inline]
+    pub fn new(num_threads: usize) -> Self {
+        assert!(num_threads > 0, "num_threads should be non-zero");
+        Self { num_threads }
+    }
+}
+
+#[cfg(feature = "std")]
+impl WorkerThreads for StdWorkerThreads {
+    #[inline]
+    fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    fn broadcast<F>(&self, f: F)
+    where
+        F: Fn(usize) + Sync,
+    {
+        let f = &f;
+        std::thread::scope(|s| {
+            for worker_id in 1..self.num_threads {
+                s.spawn(move || f(worker_id));
+            }
+            f(0);
+        });
+    }
+
+    #[inline]
+    fn relax(&self) {
+        std::thread::yield_now();
+    }
+}
+
+/// Barrier spinning on atomics, which works without `std`.
+///
+/// It is poisoned if a worker panics, so that the others panic instead of waiting forever.
+struct SpinBarrier {
+    num_threads: usize,
+    count: AtomicUsize,
+    generation: AtomicUsize,
+    poisoned: AtomicBool,
+}
+
+impl SpinBarrier {
+    fn new(num_threads: usize) -> Self {
+        Self {
+            num_threads,
+            count: AtomicUsize::new(0),
+            generation: AtomicUsize::new(0),
+            poisoned: AtomicBool::new(false),
+        }
+    }
+
+    /// Panic if the barrier is poisoned while waiting.
+    fn wait(&self, relax: impl Fn()) {
+        let generation = self.generation.load(atomic::Ordering::Acquire);
+        if self.count.fetch_add(1, atomic::Ordering::AcqRel) + 1 == self.num_threads {
+            self.count.store(0, atomic::Ordering::Relaxed);
+            self.generation.fetch_add(1, atomic::Ordering::Release);
+        } else {
+            while self.generation.load(atomic::Ordering::Acquire) == generation {
+                if self.poisoned.load(atomic::Ordering::Relaxed) {
+                    panic!("another worker panicked");
+                }
+                relax();
+            }
+        }
+    }
+}
+
+/// Poison the barrier when dropped, unless it is forgotten after the worker finishes.
+struct PoisonOnUnwind<'a>(&'a SpinBarrier);
+
+impl Drop for PoisonOnUnwind<'_> {
+    fn drop(&mut self) {
+        self.0.poisoned.store(true, atomic::Ordering::Relaxed);
+    }
+}
+
+struct SharedMutPtr<T>(*mut T);
+
+// SAFETY: workers only access disjoint elements between barriers.
+unsafe impl<T: Send> Send for SharedMutPtr<T> {}
+unsafe impl<T: Send> Sync for SharedMutPtr<T> {}
+
+impl<T> SharedMutPtr<T> {
+    #[inline(always)]
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+impl SortingNetwork {
+    /// Sort `array` by custom cmp function with this network on multiple threads.
+    ///
+    /// Each stage is split into `workers.num_threads()` contiguous chunks of comparators, so the
+    /// memory access pattern of every worker only depends on the array length.
+    ///
+    /// Panic if `array.len()` does not match the network length. If `cmp` panics in a worker, the
+    /// other workers panic too, instead of waiting for it forever.
+    pub fn par_sort_by<T, F, W>(&self, array: &mut [T], workers: &W, cmp: F)
+    where
+        T: CMov + Send,
+        F: Fn(&T, &T) -> Ordering + Sync,
+        W: WorkerThreads,
+    {
+        assert_eq!(
+            array.len(),
+            self.len(),
+            "input length does not match the sorting network"
+        );
+        let num_threads = workers.num_threads();
+        assert!(num_threads > 0, "num_threads should be non-zero");
+
+        let ptr = SharedMutPtr(array.as_mut_ptr());
+        let barrier = SpinBarrier::new(num_threads);
+        workers.broadcast(|worker_id| {
+            let guard = PoisonOnUnwind(&barrier);
+            for stage in self.stages() {
+                let stage_len = stage.len();
+                let chunk_size = (stage_len + num_threads - 1) / num_threads;
+                let start = (worker_id * chunk_size).min(stage_len);
+                let end = (start + chunk_size).min(stage_len);
+                for (i, j, ascending) in stage.comparators_in(start..end) {
+                    // SAFETY: comparators within a stage touch disjoint indices, and stages are
+                    // separated by the barrier.
+                    let (a, b) = unsafe { (&mut *ptr.get().add(i), &mut *ptr.get().add(j)) };
+                    let choice = (cmp(a, b) == Ordering::Less) != ascending;
+                    <_ as CMov>::cnd_swap(a, b, choice);
+                }
+                barrier.wait(|| workers.relax());
+            }
+            mem::forget(guard);
+        });
+    }
+
+    /// Sort `array` by key with this network on multiple threads.
+    #[inline]
+    pub fn par_sort_by_key<T, F, K, W>(&self, array: &mut [T], workers: &W, f: F)
+    where
+        T: CMov + Send,
+        F: Fn(&T) -> K + Sync,
+        K: Ord,
+        W: WorkerThreads,
+    {
+        self.par_sort_by(array, workers, |a, b| f(a).cmp(&f(b)))
+    }
+
+    /// Sort `array` with this network on multiple threads.
+    #[inline]
+    pub fn par_sort<T, W>(&self, array: &mut [T], workers: &W)
+    where
+        T: CMov + Ord + Send,
+        W: WorkerThreads,
+    {
+        self.par_sort_by(array, workers, |a: &T, b: &T| a.cmp(b))
+    }
+}
+
+/// Multi-threaded bitonic sort by custom cmp function.
+#[inline]
+pub fn par_bitonic_sort_by<T, F, W>(array: &mut [T], workers: &W, cmp: F)
+where
+    T: CMov + Send,
+    F: Fn(&T, &T) -> Ordering + Sync,
+    W: WorkerThreads,
+{
+    SortingNetwork::bitonic(array.len()).par_sort_by(array, workers, cmp)
+}
+
+/// Multi-threaded bitonic sort by key.
+#[inline]
+pub fn par_bitonic_sort_by_key<T, F, K, W>(array: &mut [T], workers: &W, f: F)
+where
+    T: CMov + Send,
+    F: Fn(&T) -> K + Sync,
+    K: Ord,
+    W: WorkerThreads,
+{
+    par_bitonic_sort_by(array, workers, |a, b| f(a).cmp(&f(b)))
+}
+
+/// Multi-threaded bitonic sort.
+#[inline]
+pub fn par_bitonic_sort<T, W>(array: &mut [T], workers: &W)
+where
+    T: CMov + Ord + Send,
+    W: WorkerThreads,
+{
+    par_bitonic_sort_by(array, workers, |a: &T, b: &T| a.cmp(b))
+}
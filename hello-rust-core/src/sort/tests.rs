@@ -1,6 +1,7 @@
 use super::*;
 use proptest::collection::SizeRange;
 use proptest::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
 
 fn arb_two_sorted_vecs(
     size_range: impl Into<SizeRange>,
@@ -23,6 +24,20 @@ proptest! {
         prop_assert!(array_is_sorted_by(&input, |a, b| a.cmp(b)));
     }
 
+    #[test]
+    fn test_sort_ct(mut input in prop::collection::vec(any::<(u8, i32)>(), 0..1000)) {
+        let mut expected = input.clone();
+        expected.sort_unstable();
+        bitonic_sort_ct(&mut input);
+        prop_assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn test_sort_ct_by_key(mut input in prop::collection::vec(any::<(u8, u64)>(), 0..1000)) {
+        bitonic_sort_ct_by_key(&mut input, |x| x.0);
+        prop_assert!(array_is_sorted_by(&input, |a, b| a.0.cmp(&b.0)));
+    }
+
     #[test]
     fn test_merge((a, b) in arb_two_sorted_vecs(0..1000)) {
         let res = bitonic_merge_sorted_slices(&a, &b);
@@ -35,3 +50,138 @@ proptest! {
         prop_assert!(array_is_sorted_by(&res, |a, b| a.cmp(b)));
     }
 }
+
+proptest! {
+    #[test]
+    fn test_sort_padded(
+        (mut input, max_len) in prop::collection::vec(any::<u64>(), 0..500)
+            .prop_flat_map(|v| { let len = v.len(); (Just(v), len..1000) })
+    ) {
+        let mut expected = input.clone();
+        expected.sort_unstable();
+        bitonic_sort_padded(&mut input, max_len);
+        prop_assert_eq!(input, expected);
+    }
+}
+
+#[test]
+#[should_panic(expected = "input length exceeds the public upper bound")]
+fn test_sort_padded_too_long() {
+    let mut input = vec![3u64, 2, 1];
+    bitonic_sort_padded(&mut input, 2);
+}
+
+proptest! {
+    #[test]
+    fn test_network_same_as_recursive(input in prop::collection::vec((0u8..8, any::<u32>()), 0..1000)) {
+        let mut expected = input.clone();
+        bitonic_sort_by_key(&mut expected, |x| x.0);
+
+        let network = SortingNetwork::bitonic(input.len());
+        let mut output = input;
+        network.sort_by_key(&mut output, |x| x.0);
+        prop_assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_network_reuse(inputs in prop::collection::vec(prop::collection::vec(any::<u64>(), 37), 1..10)) {
+        let network = SortingNetwork::bitonic(37);
+        for mut input in inputs {
+            network.sort(&mut input);
+            prop_assert!(array_is_sorted_by(&input, |a, b| a.cmp(b)));
+        }
+    }
+}
+
+#[test]
+fn test_network_stages_are_independent() {
+    for len in [0, 1, 2, 7, 16, 100] {
+        let network = SortingNetwork::bitonic(len);
+        assert_eq!(network.len(), len);
+        assert_eq!(
+            network.stages().map(|s| s.len()).sum::<usize>(),
+            network.comparators().count()
+        );
+        for stage in network.stages() {
+            assert!(!stage.is_empty());
+            let chunks: Vec<_> = (0..stage.len())
+                .step_by(3)
+                .flat_map(|start| stage.comparators_in(start..start + 3))
+                .collect();
+            assert_eq!(chunks, stage.comparators().collect::<Vec<_>>());
+            let mut touched = vec![false; len];
+            for (i, j, _) in stage.comparators() {
+                assert!(i < j);
+                assert!(!touched[i] && !touched[j]);
+                touched[i] = true;
+                touched[j] = true;
+            }
+        }
+    }
+    // `k (k + 1) / 2` stages for `2^k` elements, the depth of the network.
+    assert_eq!(SortingNetwork::bitonic(16).num_stages(), 10);
+    assert_eq!(SortingNetwork::bitonic(1024).num_stages(), 55);
+}
+
+proptest! {
+    #[test]
+    fn test_par_sort(
+        input in prop::collection::vec((0u8..8, any::<u32>()), 0..1000),
+        num_threads in 1usize..5,
+    ) {
+        let mut expected = input.clone();
+        bitonic_sort_by_key(&mut expected, |x| x.0);
+
+        let mut output = input;
+        par_bitonic_sort_by_key(&mut output, &StdWorkerThreads::new(num_threads), |x| x.0);
+        prop_assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn test_par_sort_panic() {
+    let mut array: Vec<u64> = (0..1000).collect();
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        par_bitonic_sort_by(&mut array, &StdWorkerThreads::new(4), |a, b| {
+            assert!(*a != 500 && *b != 500, "bad element");
+            a.cmp(b)
+        })
+    }));
+    assert!(res.is_err());
+}
+
+proptest! {
+    #[test]
+    fn test_shuffle(input in prop::collection::vec(any::<u64>(), 0..1000), seed in any::<u64>()) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut output = input.clone();
+        oblivious_shuffle(&mut output, &mut rng);
+
+        let mut expected = input;
+        expected.sort_unstable();
+        output.sort_unstable();
+        prop_assert_eq!(output, expected);
+    }
+}
+
+#[test]
+fn test_shuffle_uniform() {
+    const LEN: usize = 4;
+    const ROUNDS: usize = 24000;
+    let mut rng = StdRng::seed_from_u64(42);
+    let mut counts = [[0usize; LEN]; LEN];
+    for _ in 0..ROUNDS {
+        let mut array: Vec<usize> = (0..LEN).collect();
+        oblivious_shuffle(&mut array, &mut rng);
+        for (pos, &item) in array.iter().enumerate() {
+            counts[item][pos] += 1;
+        }
+    }
+
+    let expected = ROUNDS / LEN;
+    for row in counts {
+        for count in row {
+            assert!(count.abs_diff(expected) < expected / 10, "{counts:?}");
+        }
+    }
+}
@@ -0,0 +1,310 @@
+//! Memory access tracing for testing oblivious algorithms.
+//!
+//! Elements of a `TracedSlice` record every read and write done through `Clone`, `CMov` and
+//! comparisons. An algorithm is oblivious if the trace only depends on the input length.
+
+use crate::cmov::{CMov, CtOrd};
+use alloc::{collections::BTreeMap, rc::Rc, vec::Vec};
+use core::{
+    cell::RefCell,
+    cmp::Ordering,
+    mem,
+    ops::{Deref, DerefMut},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+/// Location of an access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Location {
+    /// Index in the traced slice.
+    Slice(usize),
+    /// Temporary element outside of the traced slice, numbered in order of first access.
+    Temp(usize),
+}
+
+#[derive(Debug, Default)]
+struct Tracer {
+    accesses: RefCell<Vec<(AccessKind, usize)>>,
+}
+
+impl Tracer {
+    #[inline]
+    fn record<T>(&self, kind: AccessKind, ptr: *const T) {
+        self.accesses.borrow_mut().push((kind, ptr as usize));
+    }
+}
+
+/// Element which records accesses to itself.
+pub struct Traced<T> {
+    value: T,
+    tracer: Rc<Tracer>,
+}
+
+impl<T> Traced<T> {
+    /// Read the value.
+    #[inline]
+    pub fn get(&self) -> &T {
+        self.tracer.record(AccessKind::Read, self);
+        &self.value
+    }
+
+    /// Write the value.
+    #[inline]
+    pub fn set(&mut self, value: T) {
+        self.tracer.record(AccessKind::Write, self);
+        self.value = value;
+    }
+
+    /// Take the value without recording.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: Clone> Clone for Traced<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            value: self.get().clone(),
+            tracer: self.tracer.clone(),
+        }
+    }
+}
+
+impl<T: CMov> CMov for Traced<T> {
+    #[inline]
+    fn cnd_select(a: &Self, b: &Self, choice: bool) -> Self {
+        Self {
+            value: T::cnd_select(a.get(), b.get(), choice),
+            tracer: a.tracer.clone(),
+        }
+    }
+
+    #[inline]
+    fn cnd_assign(&mut self, other: &Self, choice: bool) {
+        self.tracer.record(AccessKind::Read, self);
+        self.tracer.record(AccessKind::Write, self);
+        self.value.cnd_assign(other.get(), choice);
+    }
+
+    #[inline]
+    fn cnd_swap(a: &mut Self, b: &mut Self, choice: bool) {
+        for ptr in [a as *const Self, b as *const Self] {
+            a.tracer.record(AccessKind::Read, ptr);
+            a.tracer.record(AccessKind::Write, ptr);
+        }
+        T::cnd_swap(&mut a.value, &mut b.value, choice);
+    }
+}
+
+impl<T: PartialEq> PartialEq for Traced<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl<T: Eq> Eq for Traced<T> {}
+
+impl<T: PartialOrd> PartialOrd for Traced<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.get().partial_cmp(other.get())
+    }
+}
+
+impl<T: Ord> Ord for Traced<T> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.get().cmp(other.get())
+    }
+}
+
+impl<T: CtOrd> CtOrd for Traced<T> {
+    #[inline]
+    fn ct_lt(&self, other: &Self) -> bool {
+        self.get().ct_lt(other.get())
+    }
+
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> bool {
+        self.get().ct_eq(other.get())
+    }
+}
+
+impl<T: core::fmt::Debug> core::fmt::Debug for Traced<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// Slice of elements which record accesses.
+pub struct TracedSlice<T> {
+    items: Vec<Traced<T>>,
+    tracer: Rc<Tracer>,
+}
+
+impl<T> TracedSlice<T> {
+    pub fn new<I: IntoIterator<Item = T>>(values: I) -> Self {
+        let tracer = Rc::new(Tracer::default());
+        let items = values
+            .into_iter()
+            .map(|value| Traced {
+                value,
+                tracer: tracer.clone(),
+            })
+            .collect();
+        Self { items, tracer }
+    }
+
+    /// Take the accesses recorded so far.
+    pub fn take_trace(&self) -> Vec<(AccessKind, Location)> {
+        let accesses = mem::take(&mut *self.tracer.accesses.borrow_mut());
+        let base = self.items.as_ptr() as usize;
+        let size = mem::size_of::<Traced<T>>();
+        let end = base + size * self.items.len();
+        let mut temps = BTreeMap::new();
+        accesses
+            .into_iter()
+            .map(|(kind, addr)| {
+                let location = if (base..end).contains(&addr) {
+                    Location::Slice((addr - base) / size)
+                } else {
+                    let next = temps.len();
+                    Location::Temp(*temps.entry(addr).or_insert(next))
+                };
+                (kind, location)
+            })
+            .collect()
+    }
+
+    /// Values without recording.
+    pub fn into_values(self) -> Vec<T> {
+        self.items.into_iter().map(Traced::into_inner).collect()
+    }
+}
+
+impl<T> Deref for TracedSlice<T> {
+    type Target = [Traced<T>];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<T> DerefMut for TracedSlice<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        cmov::{ct_read, ct_write},
+        compact::compact,
+        sort::{bitonic_merge_sorted_slices, bitonic_sort_by, bitonic_sort_ct},
+    };
+    use proptest::prelude::*;
+
+    fn arb_two_inputs(max_len: usize) -> impl Strategy<Value = (Vec<u64>, Vec<u64>)> {
+        (0..max_len).prop_flat_map(|len| {
+            (
+                prop::collection::vec(any::<u64>(), len),
+                prop::collection::vec(any::<u64>(), len),
+            )
+        })
+    }
+
+    fn sort_trace(input: Vec<u64>) -> Vec<(AccessKind, Location)> {
+        let mut expected = input.clone();
+        expected.sort_unstable();
+        let mut slice = TracedSlice::new(input);
+        bitonic_sort_by(&mut slice, |a, b| a.get().cmp(b.get()));
+        let trace = slice.take_trace();
+        assert_eq!(slice.into_values(), expected);
+        trace
+    }
+
+    proptest! {
+        #[test]
+        fn test_bitonic_sort_trace((a, b) in arb_two_inputs(100)) {
+            prop_assert_eq!(sort_trace(a), sort_trace(b));
+        }
+
+        #[test]
+        fn test_bitonic_sort_ct_trace((a, b) in arb_two_inputs(100)) {
+            let trace = |input| {
+                let mut slice = TracedSlice::new(input);
+                bitonic_sort_ct(&mut slice);
+                slice.take_trace()
+            };
+            prop_assert_eq!(trace(a), trace(b));
+        }
+
+        #[test]
+        fn test_bitonic_merge_trace((a, b) in arb_two_inputs(100)) {
+            let trace = |mut input: Vec<u64>| {
+                let half = input.len() / 2;
+                input.truncate(half * 2);
+                input[..half].sort_unstable();
+                input[half..].sort_unstable();
+                let slice = TracedSlice::new(input);
+                let merged = bitonic_merge_sorted_slices(&slice[..half], &slice[half..]);
+                drop(merged);
+                slice.take_trace()
+            };
+            prop_assert_eq!(trace(a), trace(b));
+        }
+
+        #[test]
+        fn test_compact_trace(
+            (a, b) in arb_two_inputs(100),
+            seed in any::<u64>(),
+        ) {
+            let trace = |input: Vec<u64>| {
+                let marks: Vec<bool> = input.iter().map(|x| (x ^ seed) & 1 == 1).collect();
+                let mut slice = TracedSlice::new(input);
+                compact(&mut slice, &marks);
+                slice.take_trace()
+            };
+            prop_assert_eq!(trace(a), trace(b));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_ct_read_write_trace(
+            input in prop::collection::vec(any::<u64>(), 1..50),
+            seed in any::<(usize, usize)>(),
+        ) {
+            let trace = |idx: usize| {
+                let mut slice = TracedSlice::new(input.clone());
+                let value = ct_read(&slice, idx % input.len());
+                ct_write(&mut slice, idx % input.len(), &value);
+                slice.take_trace()
+            };
+            prop_assert_eq!(trace(seed.0), trace(seed.1));
+        }
+    }
+
+    #[test]
+    fn test_detect_non_oblivious() {
+        let trace = |input: Vec<u64>| {
+            let slice = TracedSlice::new(input);
+            // Linear search stops at the first hit.
+            let _ = slice.iter().position(|x| *x.get() == 0);
+            slice.take_trace()
+        };
+        assert_ne!(trace(vec![0, 1, 2, 3]), trace(vec![1, 2, 3, 0]));
+    }
+}
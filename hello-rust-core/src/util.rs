@@ -1,40 +1,223 @@
+use crate::{
+    aligned::{Aligned, A32},
+    cmov::{CMov, FixedEncode},
+    sort::bitonic_sort_by,
+};
 use alloc::vec::Vec;
+use core::fmt;
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Point of a fixed dimension `D`, which is moved by `cmov_bytes_a32`.
+pub type AlignedPoint<const D: usize> = Aligned<A32, [f64; D]>;
+
+/// Largest dimension of a point encoded by `FixedEncode`.
+pub const MAX_FIXED_DIM: usize = 16;
+
+/// Point of a dynamic dimension. CMov panics if the dimensions differ.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, CMov, FixedEncode)]
 pub struct Point {
+    #[fixed_encode(capacity = MAX_FIXED_DIM)]
     pub point_vec: Vec<f64>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Point {
+    /// Convert into a point of fixed dimension `D`.
+    ///
+    /// Panic if the dimension is not `D`.
+    pub fn to_aligned<const D: usize>(&self) -> AlignedPoint<D> {
+        let point: [f64; D] = self
+            .point_vec
+            .as_slice()
+            .try_into()
+            .expect("dimension does not match");
+        Aligned::new(point)
+    }
+}
+
+impl<const D: usize> From<AlignedPoint<D>> for Point {
+    #[inline]
+    fn from(point: AlignedPoint<D>) -> Self {
+        Self {
+            point_vec: point.into_inner().to_vec(),
+        }
+    }
+}
+
+/// Distance between two points. CMov panics if the numbers or dimensions of points differ.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, CMov, FixedEncode)]
 pub struct L2Dist {
+    #[fixed_encode(capacity = 2)]
     pub points: Vec<Point>,
     pub dist: f64,
 }
 
-pub fn compute_l2_distance(p1: &Point, p2: &Point) -> L2Dist {
+fn l2_distance(p1: &Point, p2: &Point) -> f64 {
     let mut sum = 0.0;
     for i in 0..p1.point_vec.len() {
         sum += (p1.point_vec[i] - p2.point_vec[i]) * (p1.point_vec[i] - p2.point_vec[i]);
     }
+    libm::sqrt(sum)
+}
+
+/// Error of computing the distance between points of different dimensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimensionMismatch(pub usize, pub usize);
+
+impl fmt::Display for DimensionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dimensions {} and {} differ", self.0, self.1)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DimensionMismatch {}
+
+/// Fail if the dimensions of the points differ, instead of panicking in the enclave.
+pub fn compute_l2_distance(p1: &Point, p2: &Point) -> Result<L2Dist, DimensionMismatch> {
+    if p1.point_vec.len() != p2.point_vec.len() {
+        return Err(DimensionMismatch(p1.point_vec.len(), p2.point_vec.len()));
+    }
     let result = L2Dist {
         points: vec![p1.clone(), p2.clone()],
-        dist: libm::sqrt(sum),
+        dist: l2_distance(p1, p2),
     };
-    result
+    Ok(result)
+}
+
+/// L2 distance between two points of fixed dimension.
+pub fn aligned_l2_distance<const D: usize>(p1: &AlignedPoint<D>, p2: &AlignedPoint<D>) -> f64 {
+    let mut sum = 0.0;
+    for (a, b) in p1.iter().zip(p2.iter()) {
+        sum += (a - b) * (a - b);
+    }
+    libm::sqrt(sum)
+}
+
+/// Bitonic sort of distances in ascending order.
+#[inline]
+pub fn bitonic_sort_by_dist(dists: &mut [L2Dist]) {
+    bitonic_sort_by(dists, |a, b| a.dist.total_cmp(&b.dist))
+}
+
+/// Oblivious k-nearest-neighbour query. Return the `k` points of `dataset` closest to `query`,
+/// in ascending order of distance. Ties are broken by the position in `dataset`.
+///
+/// Every distance is inserted into a buffer of `k` candidates with CMOV, and the results are
+/// fetched by linear scans, so the memory access pattern only depends on `dataset.len()`, `k`
+/// and the dimension.
+pub fn knn(query: &Point, dataset: &[Point], k: usize) -> Vec<L2Dist> {
+    let k = k.min(dataset.len());
+    // (distance, index). Distances are non-negative, so they are ordered as their bits.
+    let mut buffer = vec![(u64::MAX, usize::MAX); k];
+    for (idx, point) in dataset.iter().enumerate() {
+        let mut candidate = (l2_distance(query, point).to_bits(), idx);
+        for slot in buffer.iter_mut() {
+            let choice = candidate.0 < slot.0;
+            <_ as CMov>::cnd_swap(slot, &mut candidate, choice);
+        }
+    }
+
+    buffer
+        .into_iter()
+        .map(|(dist, idx)| {
+            let mut point_vec = vec![0u64; query.point_vec.len()];
+            for (i, point) in dataset.iter().enumerate() {
+                let hit = i == idx;
+                for (dst, src) in point_vec.iter_mut().zip(&point.point_vec) {
+                    dst.cnd_assign(&src.to_bits(), hit);
+                }
+            }
+            let point = Point {
+                point_vec: point_vec.into_iter().map(f64::from_bits).collect(),
+            };
+            L2Dist {
+                points: vec![query.clone(), point],
+                dist: f64::from_bits(dist),
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Point, compute_l2_distance};
+    use super::*;
+    use crate::cmov::CMov;
+    use proptest::prelude::*;
 
     #[test]
     fn test_l2_dist() {
         let a = Point{point_vec: vec![1.0, 0.0]};
         let b = Point{point_vec: vec![0.0, 0.0]};
 
-        let res = compute_l2_distance(&a, &b);
+        let res = compute_l2_distance(&a, &b).unwrap();
 
         println!("{:?}", res)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_l2_dist_dimension_mismatch() {
+        let a = Point { point_vec: vec![1.0, 0.0] };
+        let b = Point { point_vec: vec![0.0, 0.0, 0.0] };
+        assert_eq!(compute_l2_distance(&a, &b).unwrap_err(), DimensionMismatch(2, 3));
+    }
+
+    fn arb_point(dim: usize) -> impl Strategy<Value = Point> {
+        prop::collection::vec(-100.0..100.0f64, dim).prop_map(|point_vec| Point { point_vec })
+    }
+
+    proptest! {
+        #[test]
+        fn test_knn(
+            (query, dataset) in (1usize..8).prop_flat_map(|dim| {
+                (arb_point(dim), prop::collection::vec(arb_point(dim), 0..50))
+            }),
+            k in 0usize..60,
+        ) {
+            let mut expected: Vec<_> = dataset.iter().map(|p| compute_l2_distance(&query, p).unwrap()).collect();
+            expected.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap());
+            expected.truncate(k);
+
+            let res = knn(&query, &dataset, k);
+            prop_assert_eq!(res.len(), expected.len());
+            for (res, expected) in res.iter().zip(&expected) {
+                prop_assert_eq!(res.dist, expected.dist);
+                prop_assert_eq!(&res.points[0].point_vec, &expected.points[0].point_vec);
+                prop_assert_eq!(&res.points[1].point_vec, &expected.points[1].point_vec);
+            }
+        }
+    }
+
+    #[test]
+    fn test_aligned_point() {
+        let a = Point { point_vec: vec![1.0, 2.0, 3.0, 4.0] };
+        let b = Point { point_vec: vec![0.0, 2.0, 3.0, 4.0] };
+        let mut x = a.to_aligned::<4>();
+        let y = b.to_aligned::<4>();
+        assert_eq!(aligned_l2_distance(&x, &y), compute_l2_distance(&a, &b).unwrap().dist);
+
+        x.cnd_assign(&y, false);
+        assert_eq!(Point::from(x).point_vec, a.point_vec);
+        x.cnd_assign(&y, true);
+        assert_eq!(Point::from(x).point_vec, b.point_vec);
+    }
+
+    proptest! {
+        #[test]
+        fn test_bitonic_sort_by_dist(
+            (query, dataset) in (1usize..8).prop_flat_map(|dim| {
+                (arb_point(dim), prop::collection::vec(arb_point(dim), 0..50))
+            }),
+        ) {
+            let mut dists: Vec<_> = dataset.iter().map(|p| compute_l2_distance(&query, p).unwrap()).collect();
+            let mut expected: Vec<_> = dists.iter().map(|d| d.dist).collect();
+            expected.sort_by(|a, b| a.total_cmp(b));
+
+            bitonic_sort_by_dist(&mut dists);
+            for d in dists.iter() {
+                prop_assert_eq!(compute_l2_distance(&d.points[0], &d.points[1]).unwrap().dist, d.dist);
+            }
+            let dists: Vec<_> = dists.iter().map(|d| d.dist).collect();
+            prop_assert_eq!(dists, expected);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use proc_macro2::Ident;
+use proc_macro_error::{abort, abort_call_site, proc_macro_error};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, FnArg, ItemTrait, Pat, ReturnType, TraitItem, Type};
+
+/// Generate the typed ecall layer of an enclave API trait.
+///
+/// Every method takes `&self` and arguments which are `Serialize + DeserializeOwned`, and returns
+/// a serializable `Result` whose error converts from `EcallError`. A call is encoded by postcard
+/// as the index of the method followed by the arguments, so that all methods share a single
+/// byte-buffer ecall.
+///
+/// For `trait Foo`, it generates `FooClient<T>`, which implements `Foo` on the host by sending
+/// calls through the `EcallTransport` `T`, and `dispatch_foo`, which decodes a call in the
+/// enclave, runs it on an implementation of `Foo`, and encodes the response.
+#[proc_macro_attribute]
+#[proc_macro_error]
+pub fn enclave_api(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    if !attr.is_empty() {
+        abort_call_site!("enclave_api takes no arguments");
+    }
+    let input = parse_macro_input!(item as ItemTrait);
+    if !input.generics.params.is_empty() {
+        abort!(input.generics.span(), "generic trait is not supported");
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let client = format_ident!("{}Client", name);
+    let dispatch = format_ident!("dispatch_{}", to_snake_case(&name.to_string()));
+    let methods: Vec<_> = input.items.iter().map(parse_method).collect();
+
+    let client_methods = methods.iter().enumerate().map(|(i, method)| {
+        let index = i as u32;
+        let Method { ident, args, output } = method;
+        let (arg_names, arg_types): (Vec<_>, Vec<_>) = args.iter().cloned().unzip();
+        quote! {
+            fn #ident(&self, #(#arg_names: #arg_types),*) -> #output {
+                let __request = ::hello_rust_core::ecall::encode(&(#index, (#(&#arg_names,)*)))?;
+                let __response = ::hello_rust_core::ecall::EcallTransport::ecall(&self.0, &__request)?;
+                ::hello_rust_core::ecall::decode::<
+                    ::core::result::Result<#output, ::hello_rust_core::ecall::EcallError>
+                >(&__response)??
+            }
+        }
+    });
+
+    let dispatch_arms = methods.iter().enumerate().map(|(i, method)| {
+        let index = i as u32;
+        let Method { ident, args, .. } = method;
+        let (arg_names, arg_types): (Vec<_>, Vec<_>) = args.iter().cloned().unzip();
+        quote! {
+            #index => {
+                let (#(#arg_names,)*): (#(#arg_types,)*) =
+                    ::hello_rust_core::ecall::decode(__args)?;
+                ::hello_rust_core::ecall::encode(&::core::result::Result::<
+                    _,
+                    ::hello_rust_core::ecall::EcallError,
+                >::Ok(__api.#ident(#(#arg_names),*)))
+            }
+        }
+    });
+
+    let client_doc = format!("Host stub of `{name}`, which sends calls through the transport.");
+    let dispatch_doc = format!(
+        "Decode a call of `{name}` from `request`, run it on `api` and encode the response."
+    );
+
+    let expanded = quote! {
+        #input
+
+        #[doc = #client_doc]
+        #vis struct #client<T>(pub T);
+
+        impl<T: ::hello_rust_core::ecall::EcallTransport> #name for #client<T> {
+            #(#client_methods)*
+        }
+
+        #[doc = #dispatch_doc]
+        #vis fn #dispatch<A: #name + ?Sized>(
+            __api: &A,
+            request: &[u8],
+        ) -> ::hello_rust_core::ecall::__private::Vec<u8> {
+            ::hello_rust_core::ecall::dispatch(request, |__method, __args| match __method {
+                #(#dispatch_arms)*
+                _ => ::core::result::Result::Err(
+                    ::hello_rust_core::ecall::EcallError::UnknownMethod(__method),
+                ),
+            })
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// Signature of a method of the API.
+struct Method {
+    ident: Ident,
+    args: Vec<(Ident, Type)>,
+    output: Type,
+}
+
+fn parse_method(item: &TraitItem) -> Method {
+    let sig = match item {
+        TraitItem::Fn(f) => &f.sig,
+        _ => abort!(item.span(), "only methods are supported"),
+    };
+    if !sig.generics.params.is_empty() {
+        abort!(sig.generics.span(), "generic method is not supported");
+    }
+    if let Some(asyncness) = sig.asyncness {
+        abort!(asyncness.span(), "async method is not supported");
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver))
+            if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => abort!(sig.span(), "method must take `&self`"),
+    }
+    let args = inputs
+        .map(|arg| match arg {
+            FnArg::Typed(arg) => match *arg.pat {
+                Pat::Ident(ref pat) => (pat.ident.clone(), (*arg.ty).clone()),
+                _ => abort!(arg.pat.span(), "argument must be an identifier"),
+            },
+            FnArg::Receiver(_) => unreachable!(),
+        })
+        .collect();
+
+    let output = match sig.output {
+        ReturnType::Type(_, ref ty) => (**ty).clone(),
+        ReturnType::Default => abort!(sig.span(), "method must return a `Result`"),
+    };
+
+    Method {
+        ident: sig.ident.clone(),
+        args,
+        output,
+    }
+}
+
+/// Convert a type name in upper camel case to snake case.
+fn to_snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
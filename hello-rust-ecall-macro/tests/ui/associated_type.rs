@@ -0,0 +1,10 @@
+use hello_rust_core::ecall::enclave_api;
+
+#[enclave_api]
+trait Api {
+    type Output;
+
+    fn get(&self) -> hello_rust_core::ecall::Result<u32>;
+}
+
+fn main() {}
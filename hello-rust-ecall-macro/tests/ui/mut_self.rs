@@ -0,0 +1,8 @@
+use hello_rust_core::ecall::enclave_api;
+
+#[enclave_api]
+trait Api {
+    fn get(&mut self) -> hello_rust_core::ecall::Result<u32>;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use hello_rust_core::ecall::enclave_api;
+
+#[enclave_api]
+trait Api {
+    fn run(&self, value: u32);
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use hello_rust_core::ecall::enclave_api;
+
+#[enclave_api]
+trait Api {
+    fn add(&self, (a, b): (u32, u32)) -> hello_rust_core::ecall::Result<u32>;
+}
+
+fn main() {}
@@ -1,26 +1,41 @@
-use alloc::{vec::{Vec}, slice}; 
-use serde::{Serialize, Deserialize};
+use alloc::{vec::{Vec}, slice};
+use serde::Serialize;
 use sgx_types::*;
-use hello_rust_core::util::{Point, L2Dist, compute_l2_distance};
+use hello_rust_core::api::{EnclaveApi, dispatch_enclave_api};
+use hello_rust_core::ecall::Result;
+use hello_rust_core::util::{L2Dist, Point, compute_l2_distance};
+
+/// Initial size of the buffer for fetching point pairs from the host.
+const FETCH_BUFFER_SIZE: usize = 64 * 1024;
+/// Size limit of the buffer for fetching point pairs, since the host chooses the size. The ocall
+/// copies the buffer on the untrusted stack of the calling thread, which is 2 MiB by default.
+const MAX_FETCH_BUFFER_SIZE: usize = 256 * 1024;
+/// Number of keys sent per fetch, since the ocall copies them on the untrusted stack too.
+const MAX_FETCH_KEYS: usize = 4096;
+/// Size limit of the frames of results, since the host chooses the frame size.
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
 
 extern "C" {
-    fn ocall_get_outside_data_len(
+    fn ocall_fetch_point_pairs(
         retval: *mut i32,
-        query_key: usize,
-        data_len: *mut usize
+        keys: *const usize,
+        num_keys: usize,
+        buf: *mut u8,
+        buf_len: usize,
+        num_fetched: *mut usize,
+        required_len: *mut usize,
     ) -> sgx_status_t;
 
-    fn ocall_outside_data(
+    fn ocall_return_results(
         retval: *mut i32,
-        query_key: usize,
-        data: *mut u8,
-        data_len: usize,
+        frame: *const u8,
+        frame_len: usize,
     ) -> sgx_status_t;
 
-    fn ocall_return_result(
+    fn ocall_api_response(
         retval: *mut i32,
-        result_bytes: *const u8,
-        bytes_len: usize,
+        response: *const u8,
+        response_len: usize,
     ) -> sgx_status_t;
 }
 
@@ -30,49 +45,185 @@ pub unsafe extern "C" fn ecall_sgx_add(a: f64, b:f64) -> f64 {
     return a+b;
 }
 
-#[no_mangle]
-pub unsafe extern "C" fn ecall_sgx_l2_dist(key: usize) -> i32 {
-    let mut retval = 0;
-    let mut data_len = 0;
-
-    let sgx_ret = ocall_get_outside_data_len(
-        &mut retval as *mut _,
-        key, 
-        &mut data_len as *mut _
-    );
-    if sgx_ret != sgx_status_t::SGX_SUCCESS || retval != 0 {
-        std::eprintln!("Failed to get outside data length, status={}.", sgx_ret);
-        return 1;
+/// Fetch the point pairs of `keys` from the host, with one ocall per buffer of pairs. Every ocall
+/// sends at most `MAX_FETCH_KEYS` of the keys which are not fetched yet.
+///
+/// The buffer grows if a single pair does not fit, up to `MAX_FETCH_BUFFER_SIZE`.
+unsafe fn fetch_point_pairs(keys: &[usize]) -> Option<Vec<(Point, Point)>> {
+    let mut pairs = Vec::with_capacity(keys.len());
+    let mut buf = alloc::vec![0u8; FETCH_BUFFER_SIZE];
+    while pairs.len() < keys.len() {
+        let rest = &keys[pairs.len()..];
+        let rest = &rest[..rest.len().min(MAX_FETCH_KEYS)];
+        let mut retval = 0;
+        let mut num_fetched = 0;
+        let mut required_len = 0;
+        let sgx_ret = ocall_fetch_point_pairs(
+            &mut retval as *mut _,
+            rest.as_ptr(),
+            rest.len(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut num_fetched as *mut _,
+            &mut required_len as *mut _,
+        );
+        if sgx_ret != sgx_status_t::SGX_SUCCESS || retval != 0 {
+            std::eprintln!("Failed to fetch point pairs, status={}, retval={}.", sgx_ret, retval);
+            return None;
+        }
+        if num_fetched > rest.len() || (num_fetched == 0 && required_len <= buf.len()) {
+            std::eprintln!("Invalid point pair batch from the host.");
+            return None;
+        }
+        if num_fetched == 0 {
+            if required_len > MAX_FETCH_BUFFER_SIZE {
+                std::eprintln!("Point pair of {} bytes is too large.", required_len);
+                return None;
+            }
+            buf.resize(required_len, 0);
+            continue;
+        }
+
+        let mut bytes = &buf[..];
+        for _ in 0..num_fetched {
+            match postcard::take_from_bytes::<(Point, Point)>(bytes) {
+                Ok((pair, remaining)) => {
+                    pairs.push(pair);
+                    bytes = remaining;
+                }
+                Err(_) => {
+                    std::eprintln!("Failed to decode point pairs.");
+                    return None;
+                }
+            }
+        }
     }
+    Some(pairs)
+}
 
-    let mut vec_bytes: Vec<u8> = Vec::with_capacity(data_len);
-    let sgx_ret = ocall_outside_data(
-        &mut retval as *mut _,
-        key,
-        vec_bytes.as_mut_ptr() as *mut _,
-        data_len,
-    );
+/// Results encoded by postcard into frames of about `frame_size` bytes, which are returned to
+/// the host with one ocall per frame.
+struct ResultSink {
+    frame: Vec<u8>,
+    frame_size: usize,
+}
 
-    if sgx_ret != sgx_status_t::SGX_SUCCESS || retval != 0 {
-        std::eprintln!("Failed to get outside data, status={}.", sgx_ret);
-        return 1;
+impl ResultSink {
+    /// Frames larger than `MAX_FRAME_SIZE` are shrunk to it. Return `None` if `frame_size` is
+    /// zero.
+    fn new(frame_size: usize) -> Option<Self> {
+        if frame_size == 0 {
+            std::eprintln!("Invalid frame size 0.");
+            return None;
+        }
+        let frame_size = frame_size.min(MAX_FRAME_SIZE);
+        Some(Self { frame: Vec::with_capacity(frame_size), frame_size })
+    }
+
+    /// Append `result` to the frame, flushing first if it does not fit.
+    ///
+    /// A result larger than the frame size is returned in a frame of its own.
+    unsafe fn push<T: Serialize>(&mut self, result: &T) -> Option<()> {
+        let bytes = match postcard::to_allocvec(result) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                std::eprintln!("Failed to encode result.");
+                return None;
+            }
+        };
+        if !self.frame.is_empty() && self.frame.len() + bytes.len() > self.frame_size {
+            self.flush()?;
+        }
+        self.frame.extend_from_slice(&bytes);
+        if self.frame.len() >= self.frame_size {
+            self.flush()?;
+        }
+        Some(())
     }
-    vec_bytes.set_len(data_len);
 
-    let points: Vec<Point> = postcard::from_bytes(&vec_bytes).unwrap();
+    /// Return the results in the frame to the host.
+    unsafe fn flush(&mut self) -> Option<()> {
+        if self.frame.is_empty() {
+            return Some(());
+        }
+        let mut retval: i32 = 0;
+        let sgx_ret = ocall_return_results(&mut retval as *mut _, self.frame.as_ptr(), self.frame.len());
+        if sgx_ret != sgx_status_t::SGX_SUCCESS || retval != 0 {
+            std::eprintln!("[Enclave Error] Failed to return l2 distance results.");
+            std::eprintln!(" DETAIL: sgx_ret={}, retval={}.", sgx_ret, retval);
+            return None;
+        }
+        self.frame.clear();
+        Some(())
+    }
+}
 
-    let l2 = compute_l2_distance(&points[0], &points[1]);
-    
-    let ret_bytes = postcard::to_allocvec(&l2).unwrap();
-    let bytes_len = ret_bytes.len();
+unsafe fn compute_l2_dist(keys: &[usize], frame_size: usize) -> i32 {
+    let pairs = match fetch_point_pairs(keys) {
+        Some(pairs) => pairs,
+        None => return 1,
+    };
 
-    let mut retval: i32 = 0;
-    let sgx_ret = ocall_return_result(&mut retval as *mut _, ret_bytes.as_ptr(), bytes_len);
+    let mut sink = match ResultSink::new(frame_size) {
+        Some(sink) => sink,
+        None => return 1,
+    };
+    for (a, b) in pairs.iter() {
+        let l2 = match compute_l2_distance(a, b) {
+            Ok(l2) => l2,
+            Err(err) => {
+                std::eprintln!("Failed to compute l2 distance: {}.", err);
+                return 1;
+            }
+        };
+        if sink.push(&l2).is_none() {
+            return 1;
+        }
+    }
+    match sink.flush() {
+        Some(()) => 0,
+        None => 1,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ecall_sgx_l2_dist(key: usize, frame_size: usize) -> i32 {
+    compute_l2_dist(&[key], frame_size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn ecall_sgx_l2_dist_batch(
+    keys: *const usize,
+    num_keys: usize,
+    frame_size: usize,
+) -> i32 {
+    if num_keys == 0 {
+        return 0;
+    }
+    compute_l2_dist(slice::from_raw_parts(keys, num_keys), frame_size)
+}
 
+/// Implementation of the typed enclave API.
+struct Enclave;
+
+impl EnclaveApi for Enclave {
+    fn l2_dist(&self, pairs: Vec<(Point, Point)>) -> Result<Vec<L2Dist>> {
+        pairs.iter().map(|(a, b)| Ok(compute_l2_distance(a, b)?)).collect()
+    }
+}
+
+/// Single ecall of the typed enclave API, which returns the response by `ocall_api_response`.
+#[no_mangle]
+pub unsafe extern "C" fn ecall_api(request: *const u8, request_len: usize) -> i32 {
+    let request = if request_len == 0 { &[][..] } else { slice::from_raw_parts(request, request_len) };
+    let response = dispatch_enclave_api(&Enclave, request);
+
+    let mut retval: i32 = 0;
+    let sgx_ret = ocall_api_response(&mut retval as *mut _, response.as_ptr(), response.len());
     if sgx_ret != sgx_status_t::SGX_SUCCESS || retval != 0 {
-        std::eprintln!("[Enclave Error] Failed to return l2 distance result.");
+        std::eprintln!("[Enclave Error] Failed to return api response.");
         std::eprintln!(" DETAIL: sgx_ret={}, retval={}.", sgx_ret, retval);
         return 1;
     }
-    return 0;
-}
\ No newline at end of file
+    0
+}
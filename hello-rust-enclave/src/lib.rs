@@ -6,3 +6,4 @@ extern crate alloc;
 extern crate sgx_tstd as std;
 
 pub mod enclave_code;
+pub mod workers;
@@ -0,0 +1,41 @@
+use hello_rust_core::sort::WorkerThreads;
+
+/// Workers backed by `sgx_tstd::thread`. Each worker occupies one TCS.
+#[derive(Debug, Clone, Copy)]
+pub struct SgxWorkerThreads {
+    num_threads: usize,
+}
+
+impl SgxWorkerThreads {
+    /// Panic if `num_threads` is zero.
+    #[inline]
+    pub fn new(num_threads: usize) -> Self {
+        assert!(num_threads > 0, "num_threads should be non-zero");
+        Self { num_threads }
+    }
+}
+
+impl WorkerThreads for SgxWorkerThreads {
+    #[inline]
+    fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    fn broadcast<F>(&self, f: F)
+    where
+        F: Fn(usize) + Sync,
+    {
+        let f = &f;
+        std::thread::scope(|s| {
+            for worker_id in 1..self.num_threads {
+                s.spawn(move || f(worker_id));
+            }
+            f(0);
+        });
+    }
+
+    #[inline]
+    fn relax(&self) {
+        std::thread::yield_now();
+    }
+}